@@ -1,3 +1,95 @@
+// region: MODULES
+
+#[cfg(feature = "cache")]
+mod cache;
+
+#[cfg(feature = "serde")]
+mod deprecated;
+
+#[cfg(all(
+    unix,
+    any(
+        feature = "yaml",
+        feature = "json",
+        feature = "toml",
+        feature = "ron",
+        feature = "json5",
+        feature = "ini",
+        feature = "dotenv",
+        feature = "xml",
+        feature = "kdl"
+    )
+))]
+mod discovery;
+
+#[cfg(feature = "serde")]
+mod format;
+
+#[cfg(feature = "kdl")]
+mod kdl;
+
+#[cfg(feature = "serde")]
+mod resolve;
+
+mod source;
+
+#[cfg(all(test, feature = "serde"))]
+mod test_config;
+
+#[cfg(feature = "serde")]
+mod text;
+
+#[cfg(feature = "serde")]
+mod types;
+
+#[cfg(feature = "serde")]
+mod value;
+
+#[cfg(feature = "xml")]
+mod xml;
+
+// endregion: MODULES
+
+// region: RE-EXPORTS
+
+#[cfg(feature = "cache")]
+pub use cache::ConfigCache;
+
+#[cfg(all(
+    unix,
+    any(
+        feature = "yaml",
+        feature = "json",
+        feature = "toml",
+        feature = "ron",
+        feature = "json5",
+        feature = "ini",
+        feature = "dotenv",
+        feature = "xml",
+        feature = "kdl"
+    )
+))]
+pub use discovery::XdgConfigFiles;
+
+#[cfg(feature = "serde")]
+pub use format::*;
+
+#[cfg(feature = "serde")]
+pub use resolve::Finalize;
+
+pub use source::*;
+
+#[cfg(feature = "serde")]
+pub use text::TextError;
+
+#[cfg(feature = "serde")]
+pub use types::*;
+
+#[cfg(feature = "serde")]
+pub use value::{ConfigValue, ValueError};
+
+// endregion: RE-EXPORTS
+
 /// A trait to be implemented by configuration structs. Any assignable fields
 /// must be of an optional type, like for example, `Option<bool>`, or
 /// `Option<PathBuf>`.
@@ -29,38 +121,161 @@ pub trait Configuration: Default {
     /// fields were set/modified.
     fn env(&mut self) -> &mut Self;
 
+    /// Deprecated keys of the configuration struct with the keys replacing
+    /// them, as `(old_key, new_key)` pairs of dotted keys, like
+    /// `("timeout", "network.timeout")`. Override this when renaming fields,
+    /// so that config files, strings, readers and environment variables using
+    /// a deprecated key still load, with a warning naming the replacement.
+    /// Loading fails if both a deprecated key and its replacement are set.
+    ///
+    /// Config files, strings and readers using deprecated keys are loaded
+    /// through a [`ConfigValue`] tree, whose values are coerced to the types
+    /// of the fields, if the format selector supports it (see
+    /// [`ConfigDeserialize::try_value_from_string()`]). Those which do not use
+    /// any are deserialized directly.
+    fn deprecated_aliases() -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+
     #[cfg(feature = "serde")]
     /// Replace any unassigned fields (which have the value `None`) from a
     /// config string if the string is valid and has the relevant fields set.
     /// If the config string has an invalid format, an error is returned. This
     /// method must call `self.set_loaded()` if any fields were set/modified.  
-    /// The meanings of the named lifetimes, associate types, and generic types
-    /// are as follows:
-    /// - [`S`]: The string-like object which contains the configuration in one
-    /// of the supported formats
-    /// - [`D`]: A format selector generic type which is a serde `Deserializer`
-    /// trait implementor and also implements `From<S>` to permit being created
-    /// from a string. In practice, one can create a newtype to wrap the
-    /// Deserializer struct (not trait this time) which can be converted to the
-    /// configuration struct using standard serde methods. This crate
-    /// implements sample format selectors for some common types like YAML
-    /// ([`YamlFormat`]), but one can implement custom format selectors
-    /// anywhere in a similar manner.
-    fn string<'de, D>(&mut self, config_string: &'de str) -> Result<&mut Self, Error>
+    /// The meanings of the generic types are as follows:
+    /// - [`D`]: A format selector type which implements [`ConfigDeserialize`]
+    ///   for the configuration struct. This crate implements format selectors
+    ///   for some common formats like YAML ([`YamlFormat`]), but one can
+    ///   implement custom format selectors anywhere in a similar manner.
+    fn string<D>(&mut self, config_string: &str) -> Result<&mut Self, Error>
     where
-        Self: Deserialize<'de> + 'de,
-        D: ConfigDeserialize<'de, Self, Error = Box<dyn std::error::Error + 'static>>,
+        Self: DeserializeOwned,
+        D: ConfigDeserialize<Self>,
     {
-        let other_config = D::try_config_from_string(config_string)
-            .map_err(|serde_error| Box::from(serde_error))
+        let other_config = config_from_string::<Self, D, _>(
+            config_string,
+            "the config string",
+            ParseConfigStringSnafu {
+                string: config_string,
+                format: D::NAME,
+            },
+        )?;
+        self.config(other_config);
+        self.set_loaded();
+        Ok(self)
+    }
+
+    #[cfg(feature = "serde")]
+    /// Like [`string()`], but for configuration structs which borrow from the
+    /// config string (for example, those with `&'de str` fields). The format
+    /// selector must implement [`ConfigDeserializeBorrowed`].
+    fn borrowed_string<'de, D>(&mut self, config_string: &'de str) -> Result<&mut Self, Error>
+    where
+        Self: Deserialize<'de>,
+        D: ConfigDeserializeBorrowed<'de, Self>,
+    {
+        let other_config = D::try_config_from_borrowed_string(config_string)
+            .map_err(Box::from)
             .context(ParseConfigStringSnafu {
-                string: config_string.clone(),
+                string: config_string,
+                format: D::NAME,
             })?;
         self.config(other_config);
         self.set_loaded();
         Ok(self)
     }
 
+    #[cfg(feature = "serde")]
+    /// Replace any unassigned fields (which have the value `None`) from a
+    /// reader, like standard input, an in-memory buffer, or a decompressing
+    /// reader, if its contents are valid and have the relevant fields set. If
+    /// the contents cannot be read or have an invalid format, an error is
+    /// returned. This method must call `self.set_loaded()` if any fields were
+    /// set/modified.
+    fn reader<D>(&mut self, mut config_reader: impl Read) -> Result<&mut Self, Error>
+    where
+        Self: DeserializeOwned,
+        D: ConfigDeserialize<Self>,
+    {
+        let context = ParseConfigReaderSnafu { format: D::NAME };
+        let other_config = if Self::deprecated_aliases().is_empty() {
+            D::try_config_from_reader(config_reader)
+                .map_err(Box::from)
+                .context(context)?
+        } else {
+            // Deprecated keys can only be found in a string
+            let mut config_string = String::new();
+            config_reader
+                .read_to_string(&mut config_string)
+                .map_err(Box::from)
+                .context(context)?;
+            config_from_string::<Self, D, _>(&config_string, "the config reader", context)?
+        };
+        self.config(other_config);
+        self.set_loaded();
+        Ok(self)
+    }
+
+    #[cfg(feature = "serde")]
+    /// Like [`string()`], but for a stream of documents, like a YAML string
+    /// with several `---` documents or JSON Lines. The documents are overlays
+    /// in order: the first is the base layer, and every later document
+    /// overrides the fields it sets. Like any other source, the stream as a
+    /// whole only replaces unassigned fields. The format selector must
+    /// implement [`ConfigDeserializeDocuments`].
+    ///
+    /// [`string()`]: Configuration::string
+    fn string_documents<D>(&mut self, config_string: &str) -> Result<&mut Self, Error>
+    where
+        Self: DeserializeOwned,
+        D: ConfigDeserializeDocuments<Self>,
+    {
+        let documents = configs_from_string::<Self, D, _>(
+            config_string,
+            "the config string",
+            ParseConfigStringSnafu {
+                string: config_string,
+                format: D::NAME,
+            },
+        )?;
+        if let Some(other_config) = merge_documents(documents) {
+            self.config(other_config);
+            self.set_loaded();
+        }
+        Ok(self)
+    }
+
+    #[cfg(feature = "serde")]
+    /// Like [`string_documents()`], but reads the stream of documents from any
+    /// reader.
+    ///
+    /// [`string_documents()`]: Configuration::string_documents
+    fn reader_documents<D>(&mut self, mut config_reader: impl Read) -> Result<&mut Self, Error>
+    where
+        Self: DeserializeOwned,
+        D: ConfigDeserializeDocuments<Self>,
+    {
+        let context = ParseConfigReaderSnafu { format: D::NAME };
+        let documents = if Self::deprecated_aliases().is_empty() {
+            D::try_configs_from_reader(config_reader)
+                .map_err(Box::from)
+                .context(context)?
+        } else {
+            // Deprecated keys can only be found in a string
+            let mut config_string = String::new();
+            config_reader
+                .read_to_string(&mut config_string)
+                .map_err(Box::from)
+                .context(context)?;
+            configs_from_string::<Self, D, _>(&config_string, "the config reader", context)?
+        };
+        if let Some(other_config) = merge_documents(documents) {
+            self.config(other_config);
+            self.set_loaded();
+        }
+        Ok(self)
+    }
+
     #[cfg(feature = "serde")]
     /// Replace any unassigned fields (which have the value `None`) from a
     /// config filepath if the file at the supplied filepath is valid and has
@@ -68,24 +283,19 @@ pub trait Configuration: Default {
     /// the method fails silently. However, if the file exists but cannot be
     /// read, or if the file has an invalid format, an error is returned. This
     /// method must call `self.set_loaded()` if any fields were set/modified.  
-    fn filepath<'de, D>(&mut self, config_filepath: impl AsRef<Path>) -> Result<&mut Self, Error>
+    fn filepath<D>(&mut self, config_filepath: impl AsRef<Path>) -> Result<&mut Self, Error>
     where
-        Self: Deserialize<'de> + 'de,
-        D: ConfigDeserialize<'de, Self, Error = Box<dyn std::error::Error + 'static>>,
+        Self: DeserializeOwned,
+        D: ConfigDeserialize<Self>,
     {
         let config_filepath = config_filepath.as_ref().to_owned();
         if !config_filepath.exists() {
             Ok(self)
         } else {
-            let file = File::open(config_filepath.clone()).context(ReadConfigFileSnafu {
-                path: config_filepath.clone(),
+            let file = File::open(&config_filepath).context(ReadConfigFileSnafu {
+                path: &config_filepath,
             })?;
-            let file_reader = BufReader::new(file);
-            let other_config = D::try_config_from_reader(file_reader)
-                .map_err(|serde_error| Box::from(serde_error))
-                .context(ParseConfigFileSnafu {
-                    path: config_filepath.clone(),
-                })?;
+            let other_config = config_from_file::<Self, D>(file, &config_filepath)?;
             self.config(other_config);
             self.set_loaded();
             Ok(self)
@@ -94,13 +304,13 @@ pub trait Configuration: Default {
 
     #[cfg(feature = "serde")]
     /// Like [`filepath()`], but takes an optional filepath
-    fn optional_filepath<'de, D>(
+    fn optional_filepath<D>(
         &mut self,
         optional_config_filepath: Option<impl AsRef<Path>>,
     ) -> Result<&mut Self, Error>
     where
-        Self: Deserialize<'de> + 'de,
-        D: ConfigDeserialize<'de, Self, Error = Box<dyn std::error::Error + 'static>>,
+        Self: DeserializeOwned,
+        D: ConfigDeserialize<Self>,
     {
         match optional_config_filepath {
             Some(config_filepath) => self.filepath::<D>(config_filepath),
@@ -111,13 +321,10 @@ pub trait Configuration: Default {
     #[cfg(feature = "serde")]
     /// Like [`filepath()`], but additionally also fails when a file does not
     /// exist at the given config_filepath.
-    fn try_filepath<'de, D>(
-        &mut self,
-        config_filepath: impl AsRef<Path>,
-    ) -> Result<&mut Self, Error>
+    fn try_filepath<D>(&mut self, config_filepath: impl AsRef<Path>) -> Result<&mut Self, Error>
     where
-        Self: Deserialize<'de> + 'de,
-        D: ConfigDeserialize<'de, Self, Error = Box<dyn std::error::Error + 'static>>,
+        Self: DeserializeOwned,
+        D: ConfigDeserialize<Self>,
     {
         if !config_filepath.exists() {
             Err(Error::FindConfigFile {
@@ -130,13 +337,13 @@ pub trait Configuration: Default {
 
     #[cfg(feature = "serde")]
     /// Like [`try_filepath()`], but takes an optional filepath.
-    fn try_optional_filepath<'de, D>(
+    fn try_optional_filepath<D>(
         &mut self,
         optional_config_filepath: Option<impl AsRef<Path>>,
     ) -> Result<&mut Self, Error>
     where
-        Self: Deserialize<'de> + 'de,
-        D: ConfigDeserialize<'de, Self, Error = Box<dyn std::error::Error + 'static>>,
+        Self: DeserializeOwned,
+        D: ConfigDeserialize<Self>,
     {
         match optional_config_filepath {
             Some(config_filepath) => self.try_filepath::<D>(config_filepath),
@@ -146,6 +353,104 @@ pub trait Configuration: Default {
         }
     }
 
+    /// Replace any unassigned fields (which have the value `None`) from a
+    /// [`ConfigSource`] if the source is present and has the relevant fields
+    /// set. If the source fails to load, an error is returned.
+    fn source(&mut self, source: &dyn ConfigSource<Self>) -> Result<&mut Self, Error> {
+        let other_config = source.load()?;
+        Ok(self.optional_config(other_config))
+    }
+
+    #[cfg(feature = "dotenv")]
+    /// Replace any unassigned fields (which have the value `None`) from the
+    /// variables with the given prefix in a `.env` file in the current
+    /// directory or its ancestors, without modifying the environment of the
+    /// process. See [`DotenvSource`] for details.
+    fn dotenv(&mut self, prefix: &str) -> Result<&mut Self, Error>
+    where
+        Self: DeserializeOwned,
+    {
+        self.source(&DotenvSource::new(prefix))
+    }
+
+    #[cfg(all(
+        unix,
+        any(
+            feature = "yaml",
+            feature = "json",
+            feature = "toml",
+            feature = "ron",
+            feature = "json5",
+            feature = "ini",
+            feature = "dotenv",
+            feature = "xml",
+            feature = "kdl"
+        )
+    ))]
+    /// Replace any unassigned fields (which have the value `None`) from the
+    /// config files of an application in the XDG base directories, in order
+    /// of precedence. For example, with the application name `myapp` and the
+    /// file stem `config`, `~/.config/myapp/config.toml` takes precedence over
+    /// `/etc/xdg/myapp/config.toml`. See [`XdgConfigFiles`] for details.
+    fn xdg(&mut self, app_name: &str, file_stem: &str) -> Result<&mut Self, Error>
+    where
+        Self: DeserializeOwned,
+    {
+        for source in XdgConfigFiles::new(app_name, file_stem).sources()? {
+            self.source(source.as_ref())?;
+        }
+        Ok(self)
+    }
+
+    #[cfg(feature = "serde")]
+    /// Convert the configuration struct to an untyped [`ConfigValue`] tree,
+    /// for example, to list every field with [`ConfigValue::entries()`].
+    fn to_value(&self) -> Result<ConfigValue, ValueError>
+    where
+        Self: Serialize,
+    {
+        ConfigValue::from_config(self)
+    }
+
+    #[cfg(feature = "serde")]
+    /// The value of the field at a dotted key, like `network.timeout`, if the
+    /// key refers to a field. Unset fields have the value
+    /// [`ConfigValue::Null`].
+    fn get_value(&self, key: &str) -> Result<Option<ConfigValue>, ValueError>
+    where
+        Self: Serialize,
+    {
+        Ok(self.to_value()?.get(key).cloned())
+    }
+
+    #[cfg(feature = "serde")]
+    /// Set the field at a dotted key, like `network.timeout`. The value is
+    /// checked against the type of the field by converting the configuration
+    /// struct to a [`ConfigValue`] tree and back, so that an error is
+    /// returned and the configuration struct is left unchanged if the value
+    /// has the wrong type. Keys which do not refer to a field are refused in
+    /// the same way, since the conversion would silently drop them.
+    fn set_value(&mut self, key: &str, value: ConfigValue) -> Result<&mut Self, ValueError>
+    where
+        Self: Serialize + DeserializeOwned,
+    {
+        let mut tree = self.to_value()?;
+        tree.set(key, value.clone())?;
+        let is_null = value.is_null();
+        let mut other_config: Self = tree.into_config().context(SetValueSnafu { key, value })?;
+        // Values may have been coerced to the types of their fields, so only
+        // check that the key survived the round trip
+        match other_config.to_value()?.get(key) {
+            Some(other_value) if other_value.is_null() == is_null => {}
+            _ => return UnknownKeySnafu { key }.fail(),
+        }
+        if self.is_loaded() {
+            other_config.set_loaded();
+        }
+        *self = other_config;
+        Ok(self)
+    }
+
     /// Method to call to notify/record that the configuration has been loaded
     /// from any source (for example, through environment variables, through a
     /// config filepath, through a different config struct, etc.)
@@ -168,62 +473,40 @@ pub trait Configuration: Default {
 
         self
     }
-}
-
-#[cfg(feature = "serde")]
-pub trait ConfigDeserialize<'de, C>
-where
-    C: Configuration + 'de,
-{
-    type Error;
-
-    fn try_config_from_reader(reader: impl std::io::Read) -> Result<C, Self::Error>;
 
-    fn try_config_from_string(string: &'de str) -> Result<C, Self::Error>;
-}
-
-// region: FORMAT IMPLEMENTATIONS
-
-#[cfg(feature = "yaml")]
-pub struct YamlFormat {}
-
-#[cfg(feature = "yaml")]
-impl<'de, C> ConfigDeserialize<'de, C> for YamlFormat
-where
-    C: for<'de1> Deserialize<'de1> + Configuration + 'de,
-{
-    type Error = serde_yaml::Error;
-
-    fn try_config_from_reader(reader: impl std::io::Read) -> Result<C, Self::Error> {
-        serde_yaml::from_reader(reader)
-    }
-
-    fn try_config_from_string(string: &'de str) -> Result<C, Self::Error> {
-        serde_yaml::from_str(string)
+    /// Replace any unassigned fields (which have the value `None`) from
+    /// [`Default`], field by field. Unlike [`ensure_loaded()`], which only
+    /// uses the defaults if nothing was loaded at all, this also fills in the
+    /// fields which were not set by any source when others were. Like any
+    /// other source, this marks the configuration struct as loaded.
+    ///
+    /// Like [`config()`], this only replaces the fields of the struct itself:
+    /// a nested struct which is set is kept as it is, even if some of its
+    /// fields are unassigned. [`Finalize::finalize()`] also fills those.
+    ///
+    /// [`ensure_loaded()`]: Configuration::ensure_loaded
+    /// [`config()`]: Configuration::config
+    fn fill_defaults(&mut self) -> &mut Self {
+        self.config(Self::default())
     }
 }
 
-#[cfg(feature = "json")]
-pub struct JsonFormat {}
-
-#[cfg(feature = "json")]
-impl<'de, C> ConfigDeserialize<'de, C> for JsonFormat
+#[cfg(feature = "serde")]
+/// Merge a stream of documents which are overlays in order, so that later
+/// documents take precedence. There is nothing to merge if the stream has no
+/// documents.
+fn merge_documents<C>(documents: Vec<C>) -> Option<C>
 where
-    C: for<'de1> Deserialize<'de1> + Configuration + 'de,
+    C: Configuration,
 {
-    type Error = serde_json::Error;
-
-    fn try_config_from_reader(reader: impl std::io::Read) -> Result<C, Self::Error> {
-        serde_json::from_reader(reader)
-    }
-
-    fn try_config_from_string(string: &'de str) -> Result<C, Self::Error> {
-        serde_json::from_str(string)
+    let mut documents = documents.into_iter().rev();
+    let mut merged = documents.next()?;
+    for document in documents {
+        merged.config(document);
     }
+    Some(merged)
 }
 
-// endregion: FORMAT IMPLEMENTATIONS
-
 // region: ERRORS
 
 #[derive(Debug, Snafu)]
@@ -263,99 +546,282 @@ pub enum Error {
         source: std::io::Error,
     },
 
+    #[cfg(unix)]
+    #[non_exhaustive]
+    #[snafu(
+        display("could not find the XDG base directories: {source}"),
+        visibility(pub)
+    )]
+    FindXdgDirectories { source: xdg::BaseDirectoriesError },
+
+    #[cfg(unix)]
+    #[non_exhaustive]
+    #[snafu(
+        display(
+            "refusing to load the config file at {:?}, which could be modified by other \
+             users: {}",
+            path,
+            reasons
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; ")
+        ),
+        visibility(pub)
+    )]
+    InsecureConfigFile {
+        path: PathBuf,
+        reasons: Vec<Insecurity>,
+    },
+
     #[cfg(feature = "serde")]
     #[non_exhaustive]
     #[snafu(
-        display("The config file at {:?} has incorrect format: {source}", path),
+        display(
+            "The config file at {:?} has incorrect {} format: {source}",
+            path,
+            format
+        ),
         visibility(pub)
     )]
     ParseConfigFile {
         path: PathBuf,
-        source: Box<dyn std::error::Error>,
+        format: &'static str,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
     },
 
     #[cfg(feature = "serde")]
     #[non_exhaustive]
     #[snafu(
-        display("The config string {} has incorrect format: {source}", string),
+        display(
+            "The config string {} has incorrect {} format: {source}",
+            string,
+            format
+        ),
         visibility(pub)
     )]
     ParseConfigString {
         string: String,
-        source: Box<dyn std::error::Error>,
+        format: &'static str,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
     },
-}
 
-// endregion: ERRORS
+    #[cfg(feature = "serde")]
+    #[non_exhaustive]
+    #[snafu(
+        display("The config reader has incorrect {} format: {source}", format),
+        visibility(pub)
+    )]
+    ParseConfigReader {
+        format: &'static str,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
 
-// region: IMPORTS
+    #[cfg(feature = "serde")]
+    #[non_exhaustive]
+    #[snafu(
+        display(
+            "The environment variables with the prefix {:?} have incorrect values: {source}",
+            prefix
+        ),
+        visibility(pub)
+    )]
+    ParseEnvironment {
+        prefix: String,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
 
-use std::{
-    fs::File,
-    io::BufReader,
-    path::{Path, PathBuf},
-};
+    #[cfg(feature = "serde")]
+    #[non_exhaustive]
+    #[snafu(
+        display("the override {:?} is invalid: {}", argument, reason),
+        visibility(pub)
+    )]
+    InvalidOverride { argument: String, reason: String },
 
-#[cfg(feature = "serde")]
-use serde::de::Deserialize;
+    #[cfg(feature = "serde")]
+    #[non_exhaustive]
+    #[snafu(
+        display("The command-line overrides have incorrect values: {source}"),
+        visibility(pub)
+    )]
+    ParseOverrides {
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
 
-use snafu::{self, ResultExt, Snafu};
+    #[cfg(feature = "serde")]
+    #[non_exhaustive]
+    #[snafu(
+        display(
+            "both the deprecated key `{}` and its replacement `{}` are set in {}",
+            old_key,
+            new_key,
+            origin
+        ),
+        visibility(pub)
+    )]
+    DeprecatedKeyConflict {
+        old_key: String,
+        new_key: String,
+        origin: String,
+    },
 
-use crate::path::ValidPath;
+    #[cfg(feature = "serde")]
+    #[non_exhaustive]
+    #[snafu(
+        display(
+            "the deprecated key `{}` in {} cannot be moved to `{}`: {source}",
+            old_key,
+            origin,
+            new_key
+        ),
+        visibility(pub)
+    )]
+    MigrateDeprecatedKey {
+        old_key: String,
+        new_key: String,
+        origin: String,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
 
-// endregion: IMPORTS
+    #[cfg(feature = "serde")]
+    #[non_exhaustive]
+    #[snafu(
+        display(
+            "the required config fields {} are not set and have no defaults",
+            fields.join(", ")
+        ),
+        visibility(pub)
+    )]
+    MissingRequiredField { fields: Vec<String> },
 
-// region: TESTS
+    #[cfg(feature = "serde")]
+    #[non_exhaustive]
+    #[snafu(display("could not resolve the config: {source}"), visibility(pub))]
+    ResolveConfig { source: ValueError },
 
-#[cfg(test)]
-mod tests {
-    #[derive(Debug, Serialize, Deserialize)]
-    struct TestConfig {
-        my_bool: Option<bool>,
-        my_string: Option<String>,
-        #[serde(skip)]
-        _loaded: bool,
-    }
-
-    impl Default for TestConfig {
-        fn default() -> Self {
-            Self {
-                my_bool: Default::default(),
-                my_string: Default::default(),
-                _loaded: false,
+    #[cfg(feature = "serde")]
+    #[non_exhaustive]
+    #[snafu(
+        display("could not serialize the config to {} format: {source}", format),
+        visibility(pub)
+    )]
+    SerializeConfig {
+        format: &'static str,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+}
+
+impl Error {
+    /// A stable classification of the error, for matching programmatically
+    /// without depending on the fields of individual variants.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::FindConfigFile { .. } | Error::FindOptionalConfigFile { .. } => {
+                ErrorKind::NotFound
             }
+            #[cfg(unix)]
+            Error::FindXdgDirectories { .. } => ErrorKind::NotFound,
+            Error::ReadConfigFile { .. } | Error::ReadOptionalConfigFile { .. } => ErrorKind::Read,
+            #[cfg(unix)]
+            Error::InsecureConfigFile { .. } => ErrorKind::Insecure,
+            #[cfg(feature = "serde")]
+            Error::ParseConfigFile { .. }
+            | Error::ParseConfigString { .. }
+            | Error::ParseConfigReader { .. }
+            | Error::ParseEnvironment { .. }
+            | Error::InvalidOverride { .. }
+            | Error::ParseOverrides { .. }
+            | Error::MigrateDeprecatedKey { .. } => ErrorKind::Parse,
+            #[cfg(feature = "serde")]
+            Error::DeprecatedKeyConflict { .. } => ErrorKind::Conflict,
+            #[cfg(feature = "serde")]
+            Error::MissingRequiredField { .. } => ErrorKind::Missing,
+            #[cfg(feature = "serde")]
+            Error::ResolveConfig { .. } => ErrorKind::Convert,
+            #[cfg(feature = "serde")]
+            Error::SerializeConfig { .. } => ErrorKind::Serialize,
         }
     }
 
-    impl Configuration for TestConfig {
-        fn new() -> Self {
-            Self {
-                my_bool: None,
-                my_string: None,
-                _loaded: false,
-            }
+    /// The name of the format (see [`ConfigDeserialize::NAME`]) that was used
+    /// when the error occurred, if any.
+    pub fn format(&self) -> Option<&'static str> {
+        match self {
+            #[cfg(feature = "serde")]
+            Error::ParseConfigFile { format, .. }
+            | Error::ParseConfigString { format, .. }
+            | Error::ParseConfigReader { format, .. }
+            | Error::SerializeConfig { format, .. } => Some(format),
+            _ => None,
         }
+    }
+}
 
-        fn config(&mut self, other: Self) -> &mut Self {
-            self.my_bool = self.my_bool.take().or(other.my_bool);
-            self.my_string = self.my_string.take().or(other.my_string);
-            self.set_loaded();
-            self
-        }
+/// A stable classification of [`Error`] variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A config file that was required, or the XDG base directories, could
+    /// not be found.
+    NotFound,
+    /// A config file exists but could not be read.
+    Read,
+    /// A config file could be modified by users other than the current one.
+    Insecure,
+    /// A config file, string, reader, environment variables or command-line
+    /// overrides could not be parsed in the requested format.
+    Parse,
+    /// A config struct could not be serialized in the requested format.
+    Serialize,
+    /// A deprecated key and the key replacing it are both set.
+    Conflict,
+    /// Fields required by a resolved configuration struct are not set.
+    Missing,
+    /// A config struct could not be converted to another type, like its
+    /// resolved companion struct, for example, because a field of one has
+    /// a different type in the other.
+    Convert,
+}
 
-        fn env(&mut self) -> &mut Self {
-            todo!()
-        }
+// endregion: ERRORS
 
-        fn set_loaded(&mut self) {
-            self._loaded = true;
-        }
+// region: IMPORTS
 
-        fn is_loaded(&self) -> bool {
-            self._loaded
-        }
-    }
+use std::path::PathBuf;
+
+#[cfg(feature = "serde")]
+use std::{fs::File, io::Read, path::Path};
+
+#[cfg(feature = "serde")]
+use serde::{
+    de::{Deserialize, DeserializeOwned},
+    Serialize,
+};
+
+use snafu::{self, Snafu};
+
+#[cfg(feature = "serde")]
+use snafu::ResultExt;
+
+#[cfg(feature = "serde")]
+use crate::path::ValidPath;
+
+#[cfg(unix)]
+use crate::path::Insecurity;
 
+#[cfg(feature = "serde")]
+use value::{SetValueSnafu, UnknownKeySnafu};
+
+#[cfg(feature = "serde")]
+use deprecated::{config_from_file, config_from_string, configs_from_string};
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
     #[test]
     fn string_yaml() {
         let mut test_config = TestConfig::new();
@@ -391,11 +857,561 @@ mod tests {
         assert_eq!(test_config.my_string, Some(String::from("Hello World!")));
     }
 
+    #[test]
+    fn parse_error_yaml() {
+        fn assert_send_sync_static<T: Send + Sync + 'static>(_: &T) {}
+
+        let mut test_config = TestConfig::new();
+        let error = test_config.string::<YamlFormat>("my_bool: [").unwrap_err();
+
+        assert_send_sync_static(&error);
+        assert_eq!(error.kind(), ErrorKind::Parse);
+        assert_eq!(error.format(), Some("yaml"));
+        assert!(std::error::Error::source(&error)
+            .and_then(|source| source.downcast_ref::<serde_yaml::Error>())
+            .is_some());
+    }
+
+    #[test]
+    fn reader_json_toml() {
+        let mut test_config = TestConfig::new();
+
+        let test_bytes_1 = br#"{ "my_bool": false }"#;
+        let test_bytes_2 = br#"my_string = "Hello World!""#;
+
+        test_config
+            .reader::<JsonFormat>(&test_bytes_1[..])
+            .unwrap()
+            .reader::<TomlFormat>(&test_bytes_2[..])
+            .unwrap();
+        assert_eq!(test_config.my_bool, Some(false));
+        assert_eq!(test_config.my_string, Some(String::from("Hello World!")));
+
+        let error = test_config
+            .reader::<TomlFormat>(&b"my_bool = "[..])
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Parse);
+        assert_eq!(error.format(), Some("toml"));
+    }
+
+    #[test]
+    fn documents_yaml_json_lines() {
+        let mut test_config = TestConfig::new();
+        test_config
+            .string_documents::<YamlFormat>(
+                "my_bool: false\nmy_string: base\n---\nmy_string: override\n",
+            )
+            .unwrap();
+        assert_eq!(test_config.my_bool, Some(false));
+        assert_eq!(test_config.my_string, Some(String::from("override")));
+
+        let mut test_config = TestConfig::new();
+        test_config.my_bool = Some(true);
+        let test_bytes =
+            b"{ \"my_bool\": false, \"my_string\": \"base\" }\n{ \"my_string\": \"override\" }\n";
+        test_config
+            .reader_documents::<JsonFormat>(&test_bytes[..])
+            .unwrap();
+        assert_eq!(test_config.my_bool, Some(true));
+        assert_eq!(test_config.my_string, Some(String::from("override")));
+        assert!(test_config.is_loaded());
+
+        let error = TestConfig::new()
+            .string_documents::<JsonFormat>("{}\n{ \"my_bool\": 1 }")
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Parse);
+    }
+
+    #[test]
+    fn set_value() {
+        let mut test_config = TestConfig::new();
+        test_config
+            .set_value("my_string", ConfigValue::from("Hello World!"))
+            .unwrap();
+        assert_eq!(test_config.my_string, Some(String::from("Hello World!")));
+        assert_eq!(
+            test_config.get_value("my_bool").unwrap(),
+            Some(ConfigValue::Null)
+        );
+        assert_eq!(test_config.get_value("nothing").unwrap(), None);
+
+        let error = test_config
+            .set_value("my_bool", ConfigValue::from("maybe"))
+            .unwrap_err();
+        assert!(matches!(error, ValueError::SetValue { .. }));
+        assert_eq!(test_config.my_bool, None);
+
+        let error = test_config
+            .set_value("my_strng", ConfigValue::from("Hello World!"))
+            .unwrap_err();
+        assert!(matches!(error, ValueError::UnknownKey { key } if key == "my_strng"));
+        test_config
+            .set_value("my_bool", ConfigValue::from("yes"))
+            .unwrap();
+        assert_eq!(test_config.my_bool, Some(true));
+        test_config.set_value("my_bool", ConfigValue::Null).unwrap();
+        assert_eq!(test_config.my_bool, None);
+
+        let keys: Vec<String> = test_config
+            .to_value()
+            .unwrap()
+            .entries()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, ["my_bool", "my_string"]);
+    }
+
+    #[test]
+    fn runtime_format() {
+        assert_eq!(Format::from_path("config.yml"), Some(Format::Yaml));
+        assert_eq!(Format::from_name("TOML"), Some(Format::Toml));
+        assert_eq!(Format::from_path("config"), None);
+
+        let test_config: TestConfig = Format::Yaml
+            .config_from_string("my_bool: true\nmy_string: Hello")
+            .unwrap();
+        assert_eq!(test_config.my_bool, Some(true));
+        let test_string = Format::Toml.config_to_string(&test_config).unwrap();
+        let test_config: TestConfig = Format::Toml.config_from_string(&test_string).unwrap();
+        assert_eq!(test_config.my_string, Some(String::from("Hello")));
+
+        let error = Format::Json
+            .config_from_filepath::<TestConfig>("does/not/exist.json")
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn borrowed_string_yaml() {
+        #[derive(Debug, Default, Deserialize)]
+        struct BorrowedConfig<'a> {
+            my_str: Option<&'a str>,
+            #[serde(skip)]
+            _loaded: bool,
+        }
+
+        test_configuration!(BorrowedConfig<'a> { my_str });
+
+        let test_string = String::from("my_str: Hello World!");
+        let mut test_config = BorrowedConfig::new();
+        test_config
+            .borrowed_string::<YamlFormat>(&test_string)
+            .unwrap();
+        assert_eq!(test_config.my_str, Some("Hello World!"));
+    }
+
+    #[test]
+    fn deprecated_aliases() {
+        #[derive(Debug, Default, Deserialize)]
+        struct AliasedConfig {
+            my_port: Option<u16>,
+            #[serde(skip)]
+            _loaded: bool,
+        }
+
+        test_configuration!(AliasedConfig { my_port }
+            fn deprecated_aliases() -> &'static [(&'static str, &'static str)] {
+                &[("my_old_port", "my_port"), ("my_old_host", "my_port.host")]
+            }
+        );
+
+        let mut test_config = AliasedConfig::new();
+        test_config
+            .string::<YamlFormat>("my_old_port: 8080")
+            .unwrap();
+        assert_eq!(test_config.my_port, Some(8080));
+
+        let error = AliasedConfig::new()
+            .string::<YamlFormat>("my_old_port: 8080\nmy_port: 8081")
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Conflict);
+
+        // The replacement of `my_old_host` cannot be set under a number
+        let error = AliasedConfig::new()
+            .string::<YamlFormat>("my_old_host: localhost\nmy_port: 8081")
+            .unwrap_err();
+        assert!(matches!(error, Error::MigrateDeprecatedKey { .. }));
+
+        // Without deprecated keys, values are not coerced through a tree
+        let error = AliasedConfig::new()
+            .string::<YamlFormat>("my_port: \"8080\"")
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Parse);
+
+        env::set_var("DAPP_TEST_ALIASES_MY_OLD_PORT", "8082");
+        let test_config: AliasedConfig =
+            EnvSource::new("DAPP_TEST_ALIASES").load().unwrap().unwrap();
+        env::remove_var("DAPP_TEST_ALIASES_MY_OLD_PORT");
+        assert_eq!(test_config.my_port, Some(8082));
+    }
+
+    #[test]
+    fn finalize() {
+        #[derive(Debug, Serialize, Deserialize)]
+        struct ServerConfig {
+            port: Option<u16>,
+            network: Option<NetworkConfig>,
+            log_file: Option<PathBuf>,
+            #[serde(skip)]
+            _loaded: bool,
+        }
+
+        #[derive(Debug, Serialize, Deserialize)]
+        struct NetworkConfig {
+            host: Option<String>,
+            timeout: Option<u64>,
+        }
+
+        impl Default for ServerConfig {
+            fn default() -> Self {
+                Self {
+                    port: Some(8080),
+                    network: Some(NetworkConfig {
+                        host: None,
+                        timeout: Some(60),
+                    }),
+                    log_file: None,
+                    _loaded: false,
+                }
+            }
+        }
+
+        impl Configuration for ServerConfig {
+            fn new() -> Self {
+                Self {
+                    port: None,
+                    network: None,
+                    log_file: None,
+                    _loaded: false,
+                }
+            }
+
+            fn config(&mut self, other: Self) -> &mut Self {
+                self.port = self.port.take().or(other.port);
+                self.network = self.network.take().or(other.network);
+                self.log_file = self.log_file.take().or(other.log_file);
+                self.set_loaded();
+                self
+            }
+
+            fn env(&mut self) -> &mut Self {
+                self
+            }
+
+            fn set_loaded(&mut self) {
+                self._loaded = true;
+            }
+
+            fn is_loaded(&self) -> bool {
+                self._loaded
+            }
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct ResolvedServerConfig {
+            port: u16,
+            network: ResolvedNetworkConfig,
+            log_file: Option<PathBuf>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct ResolvedNetworkConfig {
+            host: String,
+            timeout: u64,
+        }
+
+        impl Finalize for ServerConfig {
+            type Resolved = ResolvedServerConfig;
+        }
+
+        let mut server_config = ServerConfig::new();
+        server_config
+            .string::<YamlFormat>("network:\n  host: localhost")
+            .unwrap();
+        assert_eq!(server_config.port, None);
+        server_config.fill_defaults();
+        assert_eq!(server_config.port, Some(8080));
+        assert!(server_config.is_loaded());
+        // Only the top-level fields are filled
+        assert_eq!(server_config.network.as_ref().unwrap().timeout, None);
+
+        // Nested fields are filled too
+        let resolved = server_config.finalize().unwrap();
+        assert_eq!(resolved.network.host, "localhost");
+        assert_eq!(resolved.network.timeout, 60);
+
+        let error = ServerConfig::new().finalize().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Missing);
+        assert!(
+            matches!(&error, Error::MissingRequiredField { fields, .. } if fields == &["network.host"])
+        );
+
+        let mut server_config = ServerConfig::new();
+        server_config
+            .string::<YamlFormat>("network:\n  host: localhost\n  timeout: 30\nport: 9090")
+            .unwrap();
+        let resolved = server_config.finalize().unwrap();
+        assert_eq!(resolved.port, 9090);
+        assert_eq!(resolved.network.host, "localhost");
+        assert_eq!(resolved.network.timeout, 30);
+        assert_eq!(resolved.log_file, None);
+    }
+
+    #[test]
+    fn loader_provenance() {
+        struct TestSource;
+
+        impl ConfigSource<TestConfig> for TestSource {
+            fn load(&self) -> Result<Option<TestConfig>, Error> {
+                let mut test_config = TestConfig::new();
+                test_config.my_string = Some(String::from("Hi World!"));
+                Ok(Some(test_config))
+            }
+
+            fn name(&self) -> String {
+                String::from("test")
+            }
+        }
+
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_filepath = test_dir.path().join("config.yaml");
+        fs::write(&test_filepath, "my_string: Hello World!").unwrap();
+        let _my_bool = crate::TestEnvVar::set("DAPP_TEST_LOADER_MY_BOOL", "yes");
+
+        let (test_config, provenance) = ConfigLoader::<TestConfig>::new()
+            .source(EnvSource::new("DAPP_TEST_LOADER"))
+            .source(FileSource::<YamlFormat>::new(
+                test_dir.path().join("missing.yaml"),
+            ))
+            .source(FileSource::<YamlFormat>::new(&test_filepath))
+            .source(TestSource)
+            .load_with_provenance()
+            .unwrap();
+        assert_eq!(test_config.my_bool, Some(true));
+        assert_eq!(test_config.my_string, Some(String::from("Hello World!")));
+        assert!(test_config.is_loaded());
+
+        let loaded: Vec<_> = provenance
+            .records()
+            .iter()
+            .map(|record| (record.name.as_str(), record.loaded))
+            .collect();
+        assert_eq!(
+            loaded,
+            [
+                ("env", true),
+                ("yaml file", false),
+                ("yaml file", true),
+                ("test", true)
+            ]
+        );
+
+        let error = ConfigLoader::<TestConfig>::new()
+            .source(FileSource::<YamlFormat>::new(test_dir.path().join("missing.yaml")).required())
+            .load()
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn source_overrides() {
+        #[derive(Debug, Default, Deserialize)]
+        struct OverriddenConfig {
+            my_port: Option<u16>,
+            my_hosts: Option<Vec<String>>,
+            my_table: Option<OverriddenTable>,
+            #[serde(skip)]
+            _loaded: bool,
+        }
+
+        #[derive(Debug, Default, Deserialize)]
+        struct OverriddenTable {
+            my_bool: Option<bool>,
+            my_string: Option<String>,
+        }
+
+        test_configuration!(OverriddenConfig {
+            my_port,
+            my_hosts,
+            my_table
+        });
+
+        let overrides = OverrideSource::parse([
+            "my_port=8080",
+            "my_hosts=[localhost, \"example.com\"]",
+            "my_table.my_bool=on",
+            "my_table.my_string='Hello, World!'",
+            "my_port=8081",
+        ])
+        .unwrap();
+        env::set_var("DAPP_TEST_OVERRIDES_MY_PORT", "9090");
+        let overridden_config = ConfigLoader::<OverriddenConfig>::new()
+            .source(overrides)
+            .source(EnvSource::new("DAPP_TEST_OVERRIDES"))
+            .load()
+            .unwrap();
+        env::remove_var("DAPP_TEST_OVERRIDES_MY_PORT");
+        assert_eq!(overridden_config.my_port, Some(8081));
+        assert_eq!(
+            overridden_config.my_hosts,
+            Some(vec![String::from("localhost"), String::from("example.com")])
+        );
+        let my_table = overridden_config.my_table.unwrap();
+        assert_eq!(my_table.my_bool, Some(true));
+        assert_eq!(my_table.my_string, Some(String::from("Hello, World!")));
+
+        for argument in ["my_port", "my_table..my_bool=on", "my_hosts=[a, b"] {
+            let error = OverrideSource::parse([argument]).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::Parse);
+        }
+        let error = ConfigSource::<OverriddenConfig>::load(
+            &OverrideSource::parse(["my_port=http"]).unwrap(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, Error::ParseOverrides { .. }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn xdg_discovery() {
+        let _xdg_env = crate::XDG_ENV_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let config_home = tempfile::tempdir().unwrap();
+        let config_dirs = [tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap()];
+        for directory in [
+            config_home.path(),
+            config_dirs[0].path(),
+            config_dirs[1].path(),
+        ] {
+            fs::create_dir(directory.join("dapp-test")).unwrap();
+        }
+        fs::write(
+            config_home.path().join("dapp-test/config.yaml"),
+            "my_string: home",
+        )
+        .unwrap();
+        fs::write(
+            config_dirs[0].path().join("dapp-test/config.toml"),
+            "my_bool = true\nmy_string = \"system\"",
+        )
+        .unwrap();
+        fs::write(
+            config_dirs[1].path().join("dapp-test/config.json"),
+            r#"{ "my_bool": false }"#,
+        )
+        .unwrap();
+        fs::write(config_dirs[1].path().join("dapp-test/other.yaml"), "").unwrap();
+        env::set_var("XDG_CONFIG_HOME", config_home.path());
+        env::set_var(
+            "XDG_CONFIG_DIRS",
+            env::join_paths(config_dirs.iter().map(|directory| directory.path())).unwrap(),
+        );
+
+        let config_files = XdgConfigFiles::new("dapp-test", "config");
+        let found: Vec<_> = config_files
+            .find()
+            .unwrap()
+            .into_iter()
+            .map(|(_, format)| format)
+            .collect();
+        assert_eq!(found, [Format::Yaml, Format::Toml, Format::Json]);
+        let candidates = config_files.candidates().unwrap();
+        assert_eq!(
+            candidates[0],
+            config_home.path().join("dapp-test/config.yaml")
+        );
+        assert!(candidates.contains(&config_dirs[0].path().join("dapp-test/config.json")));
+
+        let mut test_config = TestConfig::new();
+        test_config.xdg("dapp-test", "config").unwrap();
+        assert_eq!(test_config.my_bool, Some(true));
+        assert_eq!(test_config.my_string, Some(String::from("home")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn insecure_file_source() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_filepath = test_dir.path().join("config.yaml");
+        fs::write(&test_filepath, "my_string: Hello World!").unwrap();
+        fs::set_permissions(&test_filepath, fs::Permissions::from_mode(0o600)).unwrap();
+        let test_config: TestConfig = FileSource::<YamlFormat>::new(&test_filepath)
+            .security(SecurityPolicy::Refuse)
+            .load()
+            .unwrap()
+            .unwrap();
+        assert_eq!(test_config.my_string, Some(String::from("Hello World!")));
+
+        fs::set_permissions(&test_filepath, fs::Permissions::from_mode(0o666)).unwrap();
+        let error = ConfigSource::<TestConfig>::load(
+            &FileSource::<YamlFormat>::new(&test_filepath).security(SecurityPolicy::Refuse),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Insecure);
+        assert!(error.to_string().contains("chmod 600"));
+
+        // The file a symbolic link points to is checked
+        let link = test_dir.path().join("link.yaml");
+        symlink(&test_filepath, &link).unwrap();
+        let error = ConfigSource::<TestConfig>::load(
+            &FileSource::<YamlFormat>::new(&link).security(SecurityPolicy::Refuse),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Insecure);
+        let missing = FileSource::<YamlFormat>::new(test_dir.path().join("missing.yaml"))
+            .security(SecurityPolicy::Refuse);
+        assert!(ConfigSource::<TestConfig>::load(&missing)
+            .unwrap()
+            .is_none());
+
+        let test_config: TestConfig = FileSource::<YamlFormat>::new(&test_filepath)
+            .security(SecurityPolicy::Warn)
+            .load()
+            .unwrap()
+            .unwrap();
+        assert_eq!(test_config.my_string, Some(String::from("Hello World!")));
+    }
+
+    #[cfg(feature = "dotenv")]
+    #[test]
+    fn source_dotenv() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_filepath = test_dir.path().join(DotenvSource::FILENAME);
+        fs::write(
+            &test_filepath,
+            "OTHER_MY_BOOL=false\nMYAPP_MY_BOOL=true\nMYAPP_MY_STRING='Hello World!'\n",
+        )
+        .unwrap();
+
+        let mut test_config = TestConfig::new();
+        test_config
+            .source(&DotenvSource::new("MYAPP").filepath(&test_filepath))
+            .unwrap();
+        assert_eq!(test_config.my_bool, Some(true));
+        assert_eq!(test_config.my_string, Some(String::from("Hello World!")));
+        assert!(env::var_os("MYAPP_MY_BOOL").is_none());
+
+        let mut test_config = TestConfig::new();
+        test_config
+            .source(&DotenvSource::new("MYAPP").filepath(test_dir.path().join("missing")))
+            .unwrap();
+        assert!(!test_config.is_loaded());
+    }
+
     // region: IMPORTS
 
+    use std::{env, fs, path::PathBuf, sync::PoisonError};
+
     use serde::{Deserialize, Serialize};
 
-    use super::*;
+    use super::{
+        test_config::{test_configuration, TestConfig},
+        *,
+    };
 
     // endregion: IMPORTS
 }
@@ -0,0 +1,420 @@
+/// A cache of a merged configuration struct, for applications which load
+/// large layered configs on every invocation (for example, those called by a
+/// shell prompt). The configuration struct is stored in a compact binary
+/// encoding (MessagePack) together with a fingerprint of its sources: the
+/// path, size, modification time and inode of every source file, and the
+/// values of the relevant environment variables. As long as the fingerprint
+/// is unchanged, the configuration struct is read from the cache instead of
+/// being parsed again. If anything changed, or if the cache cannot be read,
+/// the configuration struct is loaded in full and the cache is replaced.
+///
+/// ```no_run
+/// # use dapp::config::{ConfigCache, Configuration, EnvSource, XdgConfigFiles};
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Default, Serialize, Deserialize)]
+/// # struct MyConfig {
+/// #     port: Option<u16>,
+/// #     #[serde(skip)]
+/// #     loaded: bool,
+/// # }
+/// # impl Configuration for MyConfig {
+/// #     fn new() -> Self {
+/// #         Self::default()
+/// #     }
+/// #     fn config(&mut self, other: Self) -> &mut Self {
+/// #         self.port = self.port.or(other.port);
+/// #         self
+/// #     }
+/// #     fn env(&mut self) -> &mut Self {
+/// #         self
+/// #     }
+/// #     fn set_loaded(&mut self) {
+/// #         self.loaded = true;
+/// #     }
+/// #     fn is_loaded(&self) -> bool {
+/// #         self.loaded
+/// #     }
+/// # }
+/// # fn main() -> Result<(), dapp::config::Error> {
+/// // Every candidate, so that creating a config file invalidates the cache
+/// let paths = XdgConfigFiles::new("myapp", "config").candidates()?;
+/// let config: MyConfig = ConfigCache::new("myapp", "config")?
+///     .files(paths)
+///     .env_prefix("MYAPP")
+///     .load_or_else(|| {
+///         let mut config = MyConfig::new();
+///         config.source(&EnvSource::new("MYAPP"))?.xdg("myapp", "config")?;
+///         Ok(config)
+///     })?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigCache {
+    path: PathBuf,
+    files: Vec<PathBuf>,
+    env_vars: Vec<String>,
+    env_prefixes: Vec<String>,
+}
+
+impl ConfigCache {
+    /// A cache named `cache_name` in the XDG cache directory of an
+    /// application, that is, `$XDG_CACHE_HOME/<app_name>/<cache_name>.msgpack`.
+    #[cfg(unix)]
+    pub fn new(app_name: &str, cache_name: &str) -> Result<Self, Error> {
+        let base_directories = BaseDirectories::new().context(FindXdgDirectoriesSnafu)?;
+        let path = base_directories
+            .get_cache_home()
+            .join(app_name)
+            .join(format!("{}.msgpack", cache_name));
+        Ok(Self::at(path))
+    }
+
+    /// A cache stored in the file at the given filepath.
+    pub fn at(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            files: Vec::new(),
+            env_vars: Vec::new(),
+            env_prefixes: Vec::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Invalidate the cache when the file at the given filepath changes, or
+    /// when it is created or deleted.
+    pub fn file(mut self, path: impl AsRef<Path>) -> Self {
+        self.files.push(path.as_ref().to_owned());
+        self
+    }
+
+    /// Like [`file()`](ConfigCache::file), but for several files.
+    pub fn files(mut self, paths: impl IntoIterator<Item = impl AsRef<Path>>) -> Self {
+        self.files
+            .extend(paths.into_iter().map(|path| path.as_ref().to_owned()));
+        self
+    }
+
+    /// Invalidate the cache when the environment variable with the given name
+    /// changes, or when it is set or unset.
+    pub fn env_var(mut self, name: &str) -> Self {
+        self.env_vars.push(name.to_owned());
+        self
+    }
+
+    /// Invalidate the cache when any environment variable read by an
+    /// [`EnvSource`] with the given prefix changes, or when one is set or
+    /// unset.
+    ///
+    /// [`EnvSource`]: super::EnvSource
+    pub fn env_prefix(mut self, prefix: &str) -> Self {
+        self.env_prefixes.push(format!("{}_", prefix));
+        self
+    }
+
+    /// Read the configuration struct from the cache if its sources did not
+    /// change, or load it with the closure otherwise and update the cache.
+    /// Problems with the cache itself are not errors, they are only logged,
+    /// and the configuration struct is loaded with the closure instead.
+    pub fn load_or_else<C, F>(&self, load: F) -> Result<C, Error>
+    where
+        C: Configuration + Serialize + DeserializeOwned,
+        F: FnOnce() -> Result<C, Error>,
+    {
+        let fingerprint = self.fingerprint::<C>();
+        match self.read::<C>() {
+            Ok(entry) if entry.fingerprint == fingerprint => {
+                debug!(path = ?self.path, "using the cached config");
+                let mut config = entry.config;
+                config.set_loaded();
+                return Ok(config);
+            }
+            Ok(_) => debug!(path = ?self.path, "the cached config is outdated"),
+            Err(error) => debug!(path = ?self.path, "could not read the cached config: {error}"),
+        }
+        let config = load()?;
+        if let Err(error) = self.write(&fingerprint, &config) {
+            warn!(path = ?self.path, "could not cache the config: {error}");
+        }
+        Ok(config)
+    }
+
+    /// Delete the cache, so that the next load parses every source again.
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    fn fingerprint<C>(&self) -> Fingerprint {
+        let files = self
+            .files
+            .iter()
+            .map(|path| FileFingerprint::new(path))
+            .collect();
+        let env_vars = env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+            .filter(|(name, _)| {
+                self.env_vars.contains(name)
+                    || self
+                        .env_prefixes
+                        .iter()
+                        .any(|prefix| name.starts_with(prefix))
+            })
+            .map(|(name, value)| (name, value.to_string_lossy().into_owned()))
+            .collect();
+        Fingerprint {
+            type_name: any::type_name::<C>().to_owned(),
+            files,
+            env_vars,
+        }
+    }
+
+    fn read<C>(&self) -> Result<CacheEntry<C>, Box<dyn std::error::Error>>
+    where
+        C: DeserializeOwned,
+    {
+        let file = File::open(&self.path)?;
+        Ok(rmp_serde::from_read(BufReader::new(file))?)
+    }
+
+    fn write<C>(
+        &self,
+        fingerprint: &Fingerprint,
+        config: &C,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        C: Serialize,
+    {
+        let entry = CacheEntryRef {
+            fingerprint,
+            config,
+        };
+        let bytes = rmp_serde::to_vec_named(&entry)?;
+        // The config may contain secrets, so only the user can read the cache
+        if let Some(directory) = self.path.parent() {
+            #[cfg(unix)]
+            ensure_dir(directory, 0o700)?;
+            #[cfg(not(unix))]
+            fs::create_dir_all(directory)?;
+        }
+
+        // Replace the cache at once, so that concurrent loads never read a
+        // partially written cache. The temporary file is unique even for
+        // several writers in the same process.
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let mut temporary_path = self.path.clone().into_os_string();
+        temporary_path.push(format!(
+            ".{}.{}.tmp",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let result = options
+            .open(&temporary_path)
+            .and_then(|mut file| file.write_all(&bytes))
+            .and_then(|()| fs::rename(&temporary_path, &self.path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary_path);
+        }
+        Ok(result?)
+    }
+}
+
+/// What the cached configuration struct was loaded from.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Fingerprint {
+    /// The configuration struct, so that a cache is never read into the wrong
+    /// type.
+    type_name: String,
+    files: Vec<FileFingerprint>,
+    env_vars: BTreeMap<String, String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct FileFingerprint {
+    path: PathBuf,
+    /// `None` if the file does not exist, or cannot be inspected.
+    metadata: Option<(u64, u64, u32, u64)>,
+}
+
+impl FileFingerprint {
+    fn new(path: &Path) -> Self {
+        let metadata = fs::metadata(path).ok().map(|metadata| {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .unwrap_or_default();
+            #[cfg(unix)]
+            let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+            #[cfg(not(unix))]
+            let inode = 0;
+            (
+                metadata.len(),
+                modified.as_secs(),
+                modified.subsec_nanos(),
+                inode,
+            )
+        });
+        Self {
+            path: path.to_owned(),
+            metadata,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CacheEntry<C> {
+    fingerprint: Fingerprint,
+    config: C,
+}
+
+#[derive(Serialize)]
+struct CacheEntryRef<'a, C> {
+    fingerprint: &'a Fingerprint,
+    config: &'a C,
+}
+
+// region: IMPORTS
+
+use std::{
+    any,
+    collections::BTreeMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::UNIX_EPOCH,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[cfg(unix)]
+use snafu::ResultExt;
+
+use tracing::{debug, warn};
+
+#[cfg(unix)]
+use xdg::BaseDirectories;
+
+#[cfg(unix)]
+use crate::path::ensure_dir;
+
+use super::{Configuration, Error};
+
+#[cfg(unix)]
+use super::FindXdgDirectoriesSnafu;
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(all(test, unix, feature = "yaml"))]
+mod tests {
+    #[test]
+    fn load_or_else() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_filepath = test_dir.path().join("config.yaml");
+        fs::write(&test_filepath, "my_string: Hello World!").unwrap();
+        let cache = ConfigCache::at(test_dir.path().join("cache/config.msgpack"))
+            .file(&test_filepath)
+            .env_prefix("DAPP_TEST_CACHE");
+
+        let loads = Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            let mut test_config = TestConfig::new();
+            test_config.filepath::<YamlFormat>(&test_filepath)?;
+            Ok(test_config)
+        };
+
+        let test_config: TestConfig = cache.load_or_else(load).unwrap();
+        assert_eq!(test_config.my_string, Some(String::from("Hello World!")));
+        let test_config: TestConfig = cache.load_or_else(load).unwrap();
+        assert_eq!(test_config.my_string, Some(String::from("Hello World!")));
+        assert!(test_config.is_loaded());
+        assert_eq!(loads.get(), 1);
+
+        fs::write(&test_filepath, "my_string: Hi World!").unwrap();
+        let test_config: TestConfig = cache.load_or_else(load).unwrap();
+        assert_eq!(test_config.my_string, Some(String::from("Hi World!")));
+        assert_eq!(loads.get(), 2);
+
+        env::set_var("DAPP_TEST_CACHE_MY_BOOL", "true");
+        cache.load_or_else(load).unwrap();
+        env::remove_var("DAPP_TEST_CACHE_MY_BOOL");
+        assert_eq!(loads.get(), 3);
+
+        fs::write(cache.path(), b"not a cache").unwrap();
+        cache.load_or_else(load).unwrap();
+        assert_eq!(loads.get(), 4);
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(cache.path()), 0o600);
+        assert_eq!(mode(&test_dir.path().join("cache")), 0o700);
+
+        cache.clear().unwrap();
+        assert!(!cache.path().exists());
+    }
+
+    #[test]
+    fn relative_paths() {
+        #[derive(Debug, Default, Serialize, Deserialize)]
+        struct PathConfig {
+            data: Option<ConfigRelativePath>,
+            #[serde(skip)]
+            _loaded: bool,
+        }
+
+        test_configuration!(PathConfig { data });
+
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_filepath = test_dir.path().join("config.yaml");
+        fs::write(&test_filepath, "data: ./data").unwrap();
+        let cache = ConfigCache::at(test_dir.path().join("config.msgpack")).file(&test_filepath);
+        let load = || {
+            let mut path_config = PathConfig::new();
+            path_config.filepath::<YamlFormat>(&test_filepath)?;
+            Ok(path_config)
+        };
+
+        let loaded: PathConfig = cache.load_or_else(load).unwrap();
+        let cached: PathConfig = cache
+            .load_or_else(|| panic!("the cache is outdated"))
+            .unwrap();
+        assert_eq!(cached.data, loaded.data);
+        assert_eq!(
+            cached.data.unwrap().resolved(),
+            test_dir.path().join("./data")
+        );
+    }
+
+    // region: IMPORTS
+
+    use std::{cell::Cell, os::unix::fs::PermissionsExt};
+
+    use super::{
+        super::{
+            test_config::{test_configuration, TestConfig},
+            ConfigRelativePath, YamlFormat,
+        },
+        *,
+    };
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
@@ -0,0 +1,221 @@
+/// An untyped tree whose keys can be renamed, so that deprecated keys can be
+/// migrated before the tree is deserialized into a configuration struct.
+/// Keys are dotted keys, like those of [`Configuration::deprecated_aliases()`].
+pub(crate) trait KeyTree: Sized {
+    /// Remove the value at a key, if it is set.
+    fn remove_key(&mut self, key: &str) -> Option<Self>;
+
+    /// Whether the value at a key is set.
+    fn has_key(&self, key: &str) -> bool;
+
+    /// Set the value at a key, failing if the key cannot hold a value, for
+    /// example, because one of its parents is set to a value rather than a
+    /// table.
+    fn insert_key(&mut self, key: &str, value: Self) -> Result<(), BoxedError>;
+}
+
+impl KeyTree for ConfigValue {
+    fn remove_key(&mut self, key: &str) -> Option<Self> {
+        self.remove(key).filter(|value| !value.is_null())
+    }
+
+    fn has_key(&self, key: &str) -> bool {
+        self.get(key).is_some_and(|value| !value.is_null())
+    }
+
+    fn insert_key(&mut self, key: &str, value: Self) -> Result<(), BoxedError> {
+        self.set(key, value)?;
+        Ok(())
+    }
+}
+
+impl KeyTree for Text {
+    fn remove_key(&mut self, key: &str) -> Option<Self> {
+        let path: Vec<&str> = key.split(ConfigValue::SEPARATOR).collect();
+        self.remove(&path)
+    }
+
+    fn has_key(&self, key: &str) -> bool {
+        let path: Vec<&str> = key.split(ConfigValue::SEPARATOR).collect();
+        self.get(&path).is_some()
+    }
+
+    fn insert_key(&mut self, key: &str, value: Self) -> Result<(), BoxedError> {
+        let path: Vec<&str> = key.split(ConfigValue::SEPARATOR).collect();
+        self.insert(&path, value)?;
+        Ok(())
+    }
+}
+
+/// Rename the deprecated keys of the configuration struct `C` in a tree
+/// loaded from `location` (for example, `the config file at "app.toml"`),
+/// logging a warning for every deprecated key which is set. The closure maps
+/// keys to the names used by the source in messages, for example, to the
+/// names of environment variables. If both a deprecated key and its
+/// replacement are set, or if the replacement cannot be set, an error is
+/// returned.
+pub(crate) fn migrate_deprecated_keys<C, T>(
+    tree: &mut T,
+    location: &str,
+    key_name: impl Fn(&str) -> String,
+) -> Result<(), Error>
+where
+    C: Configuration,
+    T: KeyTree,
+{
+    for &(old_key, new_key) in C::deprecated_aliases() {
+        let Some(value) = tree.remove_key(old_key) else {
+            continue;
+        };
+        let (old_name, new_name) = (key_name(old_key), key_name(new_key));
+        if tree.has_key(new_key) {
+            return DeprecatedKeyConflictSnafu {
+                old_key: old_name,
+                new_key: new_name,
+                origin: location,
+            }
+            .fail();
+        }
+        tree.insert_key(new_key, value)
+            .context(MigrateDeprecatedKeySnafu {
+                old_key: &old_name,
+                new_key: &new_name,
+                origin: location,
+            })?;
+        warn!(
+            location,
+            replacement = %new_name,
+            "`{}` in {} is deprecated, use `{}` instead",
+            old_name,
+            location,
+            new_name
+        );
+    }
+    Ok(())
+}
+
+/// Deserialize a configuration struct from a string in the format `D`,
+/// loaded from `location`, migrating its deprecated keys if the string uses
+/// any. Other strings, and those in formats which cannot be loaded as a
+/// [`ConfigValue`] tree, are deserialized directly, so that the types and the
+/// errors of the format are kept.
+pub(crate) fn config_from_string<C, D, S>(
+    string: &str,
+    location: &str,
+    context: S,
+) -> Result<C, Error>
+where
+    C: Configuration + DeserializeOwned,
+    D: ConfigDeserialize<C>,
+    S: IntoError<Error, Source = BoxedError> + Copy,
+{
+    if !C::deprecated_aliases().is_empty() {
+        if let Some(Ok(mut value)) = D::try_value_from_string(string) {
+            if uses_deprecated_keys::<C>(&value) {
+                migrate_deprecated_keys::<C, _>(&mut value, location, key_name)?;
+                return value.into_config().map_err(Box::from).context(context);
+            }
+        }
+    }
+    D::try_config_from_string(string)
+        .map_err(Box::from)
+        .context(context)
+}
+
+/// Deserialize a configuration struct from the file opened from
+/// `config_filepath` in the format `D`, like [`config_from_string()`].
+/// Relative [`ConfigRelativePath`](super::ConfigRelativePath)s are resolved
+/// against the directory of the file.
+pub(crate) fn config_from_file<C, D>(file: File, config_filepath: &Path) -> Result<C, Error>
+where
+    C: Configuration + DeserializeOwned,
+    D: ConfigDeserialize<C>,
+{
+    let mut file_reader = BufReader::new(file);
+    let context = ParseConfigFileSnafu {
+        path: config_filepath,
+        format: D::NAME,
+    };
+    with_config_filepath(config_filepath, || {
+        if C::deprecated_aliases().is_empty() {
+            D::try_config_from_reader(file_reader)
+                .map_err(Box::from)
+                .context(context)
+        } else {
+            let mut config_string = String::new();
+            file_reader
+                .read_to_string(&mut config_string)
+                .context(ReadConfigFileSnafu {
+                    path: config_filepath,
+                })?;
+            let location = format!("the config file at {:?}", config_filepath);
+            config_from_string::<C, D, _>(&config_string, &location, context)
+        }
+    })
+}
+
+/// Like [`config_from_string()`], but for every document of a stream.
+pub(crate) fn configs_from_string<C, D, S>(
+    string: &str,
+    location: &str,
+    context: S,
+) -> Result<Vec<C>, Error>
+where
+    C: Configuration + DeserializeOwned,
+    D: ConfigDeserializeDocuments<C>,
+    S: IntoError<Error, Source = BoxedError> + Copy,
+{
+    if !C::deprecated_aliases().is_empty() {
+        if let Some(Ok(values)) = D::try_values_from_string(string) {
+            if values.iter().any(uses_deprecated_keys::<C>) {
+                let mut documents = Vec::with_capacity(values.len());
+                for mut value in values {
+                    migrate_deprecated_keys::<C, _>(&mut value, location, key_name)?;
+                    documents.push(value.into_config().map_err(Box::from).context(context)?);
+                }
+                return Ok(documents);
+            }
+        }
+    }
+    D::try_configs_from_string(string)
+        .map_err(Box::from)
+        .context(context)
+}
+
+fn uses_deprecated_keys<C>(value: &ConfigValue) -> bool
+where
+    C: Configuration,
+{
+    C::deprecated_aliases()
+        .iter()
+        .any(|(old_key, _)| value.has_key(old_key))
+}
+
+/// Keys are named as they are in config files.
+fn key_name(key: &str) -> String {
+    key.to_owned()
+}
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+// region: IMPORTS
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use serde::de::DeserializeOwned;
+
+use snafu::{IntoError, ResultExt};
+
+use tracing::warn;
+
+use super::{
+    text::Text, types::with_config_filepath, ConfigDeserialize, ConfigDeserializeDocuments,
+    ConfigValue, Configuration, DeprecatedKeyConflictSnafu, Error, MigrateDeprecatedKeySnafu,
+    ParseConfigFileSnafu, ReadConfigFileSnafu,
+};
+
+// endregion: IMPORTS
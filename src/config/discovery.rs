@@ -0,0 +1,104 @@
+/// The config files of an application in the XDG base directories, found by
+/// the name of the application and the stem of the file name. For the
+/// application `myapp` and the stem `config`, the files `config.yaml`,
+/// `config.toml` and so on (for every format compiled into the crate) are
+/// searched for in `$XDG_CONFIG_HOME/myapp`, and then in `myapp` in every
+/// directory of `$XDG_CONFIG_DIRS`, with the precedence of the XDG Base
+/// Directory Specification: files in `$XDG_CONFIG_HOME` take precedence over
+/// those in `$XDG_CONFIG_DIRS`, which take precedence in the order they are
+/// listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XdgConfigFiles {
+    app_name: String,
+    file_stem: String,
+}
+
+impl XdgConfigFiles {
+    pub fn new(app_name: impl Into<String>, file_stem: impl Into<String>) -> Self {
+        Self {
+            app_name: app_name.into(),
+            file_stem: file_stem.into(),
+        }
+    }
+
+    pub fn app_name(&self) -> &str {
+        &self.app_name
+    }
+
+    pub fn file_stem(&self) -> &str {
+        &self.file_stem
+    }
+
+    /// The directories which are searched, in order of precedence, whether
+    /// they exist or not.
+    pub fn directories(&self) -> Result<Vec<PathBuf>, Error> {
+        let base_directories = BaseDirectories::new().context(FindXdgDirectoriesSnafu)?;
+        Ok(iter::once(base_directories.get_config_home())
+            .chain(base_directories.get_config_dirs())
+            .map(|directory| directory.join(&self.app_name))
+            .collect())
+    }
+
+    /// Every path where a config file is searched for, in order of
+    /// precedence, whether a file exists there or not: every extension of
+    /// every format in every directory. Files in the same directory are
+    /// ordered like [`Format::ALL`], so that, for example, `config.yaml`
+    /// takes precedence over `config.toml`.
+    pub fn candidates(&self) -> Result<Vec<PathBuf>, Error> {
+        let file_stem = &self.file_stem;
+        Ok(self
+            .directories()?
+            .into_iter()
+            .flat_map(|directory| {
+                Format::ALL
+                    .iter()
+                    .flat_map(|format| format.extensions())
+                    .map(move |extension| directory.join(format!("{}.{}", file_stem, extension)))
+            })
+            .collect())
+    }
+
+    /// The readable config files, in order of precedence, with their formats
+    /// (see [`candidates()`](XdgConfigFiles::candidates)).
+    pub fn find(&self) -> Result<Vec<(PathBuf, Format)>, Error> {
+        let files = self
+            .candidates()?
+            .into_iter()
+            .all_valid_paths(|path| path.is_file() && path.is_readable())
+            .filter_map(|path| Format::from_path(&path).map(|format| (path, format)))
+            .collect();
+        Ok(files)
+    }
+
+    /// A [`FileSource`] for every readable config file, in order of
+    /// precedence, for example, to add to a [`ConfigLoader`].
+    ///
+    /// [`FileSource`]: super::FileSource
+    /// [`ConfigLoader`]: super::ConfigLoader
+    pub fn sources<C>(&self) -> Result<Vec<Box<dyn ConfigSource<C>>>, Error>
+    where
+        C: Configuration + DeserializeOwned,
+    {
+        Ok(self
+            .find()?
+            .into_iter()
+            .map(|(path, format)| format.file_source(path))
+            .collect())
+    }
+}
+
+// region: IMPORTS
+
+use std::{iter, path::PathBuf};
+
+use serde::de::DeserializeOwned;
+
+use snafu::ResultExt;
+
+use xdg::BaseDirectories;
+
+use crate::path::{ValidPath, ValidPaths};
+
+use super::{ConfigSource, Configuration, Error, FindXdgDirectoriesSnafu, Format};
+
+// endregion: IMPORTS
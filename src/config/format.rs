@@ -0,0 +1,1318 @@
+/// To be implemented by format selectors, which deserialize an owned value
+/// (typically a configuration struct) from a string or a reader in a given
+/// format. The meanings of the generic types are as follows:
+/// - [`C`]: The type being deserialized, usually a [`Configuration`]
+///   implementor.
+///
+/// [`Configuration`]: super::Configuration
+pub trait ConfigDeserialize<C> {
+    /// A short, human-readable name for the format (for example, `"yaml"`),
+    /// which is recorded in parsing errors.
+    const NAME: &'static str;
+
+    /// The error returned by the underlying format implementation. It is
+    /// boxed into [`Error`](super::Error) and can be recovered by downcasting
+    /// [`std::error::Error::source()`].
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Deserialize from any reader, for example, a file, standard input, an
+    /// in-memory buffer, or a decompressing reader.
+    fn try_config_from_reader(reader: impl Read) -> Result<C, Self::Error>;
+
+    /// Deserialize from a string.
+    fn try_config_from_string(string: &str) -> Result<C, Self::Error>;
+
+    /// Deserialize from a string into an untyped [`ConfigValue`] tree, in
+    /// which deprecated keys can be migrated before the configuration struct
+    /// is deserialized (see [`Configuration::deprecated_aliases()`]). Format
+    /// selectors which return `None`, as by default, load configuration
+    /// structs without migrating deprecated keys.
+    ///
+    /// [`Configuration::deprecated_aliases()`]: super::Configuration::deprecated_aliases
+    fn try_value_from_string(_string: &str) -> Option<Result<ConfigValue, Self::Error>> {
+        None
+    }
+}
+
+/// An opt-in counterpart of [`ConfigDeserialize`] for types which borrow from
+/// the input string, for example, configuration structs holding `&'de str`
+/// fields. The meanings of the named lifetimes and generic types are as
+/// follows:
+/// - [`'de`]: The lifetime of the borrowed input string.
+/// - [`C`]: The type being deserialized.
+pub trait ConfigDeserializeBorrowed<'de, C> {
+    /// See [`ConfigDeserialize::NAME`].
+    const NAME: &'static str;
+
+    /// See [`ConfigDeserialize::Error`].
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Deserialize from a string that outlives the deserialized value.
+    fn try_config_from_borrowed_string(string: &'de str) -> Result<C, Self::Error>;
+}
+
+/// To be implemented by format selectors whose formats can hold a stream of
+/// documents, like YAML files with several `---` documents or JSON Lines,
+/// which are loaded as an ordered list of overlays. The meanings of the
+/// generic types are as follows:
+/// - [`C`]: The type of every document, usually a [`Configuration`]
+///   implementor.
+///
+/// [`Configuration`]: super::Configuration
+pub trait ConfigDeserializeDocuments<C> {
+    /// See [`ConfigDeserialize::NAME`].
+    const NAME: &'static str;
+
+    /// See [`ConfigDeserialize::Error`].
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Deserialize every document from any reader, in order.
+    fn try_configs_from_reader(reader: impl Read) -> Result<Vec<C>, Self::Error>;
+
+    /// Deserialize every document from a string, in order.
+    fn try_configs_from_string(string: &str) -> Result<Vec<C>, Self::Error>;
+
+    /// Like [`ConfigDeserialize::try_value_from_string()`], but for every
+    /// document.
+    fn try_values_from_string(_string: &str) -> Option<Result<Vec<ConfigValue>, Self::Error>> {
+        None
+    }
+}
+
+/// To be implemented by format selectors which can also serialize a value
+/// (typically a configuration struct) back to a string, for example, to
+/// write a default config file or to convert between formats. The meanings
+/// of the generic types are as follows:
+/// - [`C`]: The type being serialized.
+pub trait ConfigSerialize<C> {
+    /// See [`ConfigDeserialize::NAME`].
+    const NAME: &'static str;
+
+    /// See [`ConfigDeserialize::Error`].
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Serialize to a human-readable string.
+    fn try_config_to_string(config: &C) -> Result<String, Self::Error>;
+}
+
+// region: FORMAT IMPLEMENTATIONS
+
+#[cfg(feature = "yaml")]
+pub struct YamlFormat {}
+
+#[cfg(feature = "yaml")]
+impl<C> ConfigDeserialize<C> for YamlFormat
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "yaml";
+
+    type Error = serde_yaml::Error;
+
+    fn try_config_from_reader(reader: impl Read) -> Result<C, Self::Error> {
+        serde_yaml::from_reader(reader)
+    }
+
+    fn try_config_from_string(string: &str) -> Result<C, Self::Error> {
+        serde_yaml::from_str(string)
+    }
+
+    fn try_value_from_string(string: &str) -> Option<Result<ConfigValue, Self::Error>> {
+        Some(<Self as ConfigDeserialize<ConfigValue>>::try_config_from_string(string))
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl<'de, C> ConfigDeserializeBorrowed<'de, C> for YamlFormat
+where
+    C: Deserialize<'de>,
+{
+    const NAME: &'static str = "yaml";
+
+    type Error = serde_yaml::Error;
+
+    fn try_config_from_borrowed_string(string: &'de str) -> Result<C, Self::Error> {
+        serde_yaml::from_str(string)
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl<C> ConfigDeserializeDocuments<C> for YamlFormat
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "yaml";
+
+    type Error = serde_yaml::Error;
+
+    fn try_configs_from_reader(reader: impl Read) -> Result<Vec<C>, Self::Error> {
+        serde_yaml::Deserializer::from_reader(reader)
+            .map(C::deserialize)
+            .collect()
+    }
+
+    fn try_configs_from_string(string: &str) -> Result<Vec<C>, Self::Error> {
+        serde_yaml::Deserializer::from_str(string)
+            .map(C::deserialize)
+            .collect()
+    }
+
+    fn try_values_from_string(string: &str) -> Option<Result<Vec<ConfigValue>, Self::Error>> {
+        Some(<Self as ConfigDeserializeDocuments<ConfigValue>>::try_configs_from_string(string))
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl<C> ConfigSerialize<C> for YamlFormat
+where
+    C: Serialize,
+{
+    const NAME: &'static str = "yaml";
+
+    type Error = serde_yaml::Error;
+
+    fn try_config_to_string(config: &C) -> Result<String, Self::Error> {
+        serde_yaml::to_string(config)
+    }
+}
+
+#[cfg(feature = "json")]
+pub struct JsonFormat {}
+
+#[cfg(feature = "json")]
+impl<C> ConfigDeserialize<C> for JsonFormat
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "json";
+
+    type Error = serde_json::Error;
+
+    fn try_config_from_reader(reader: impl Read) -> Result<C, Self::Error> {
+        serde_json::from_reader(reader)
+    }
+
+    fn try_config_from_string(string: &str) -> Result<C, Self::Error> {
+        serde_json::from_str(string)
+    }
+
+    fn try_value_from_string(string: &str) -> Option<Result<ConfigValue, Self::Error>> {
+        Some(<Self as ConfigDeserialize<ConfigValue>>::try_config_from_string(string))
+    }
+}
+
+#[cfg(feature = "json")]
+impl<'de, C> ConfigDeserializeBorrowed<'de, C> for JsonFormat
+where
+    C: Deserialize<'de>,
+{
+    const NAME: &'static str = "json";
+
+    type Error = serde_json::Error;
+
+    fn try_config_from_borrowed_string(string: &'de str) -> Result<C, Self::Error> {
+        serde_json::from_str(string)
+    }
+}
+
+#[cfg(feature = "json")]
+/// Reads JSON Lines, or any other sequence of JSON values separated by
+/// whitespace.
+impl<C> ConfigDeserializeDocuments<C> for JsonFormat
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "json";
+
+    type Error = serde_json::Error;
+
+    fn try_configs_from_reader(reader: impl Read) -> Result<Vec<C>, Self::Error> {
+        serde_json::Deserializer::from_reader(reader)
+            .into_iter()
+            .collect()
+    }
+
+    fn try_configs_from_string(string: &str) -> Result<Vec<C>, Self::Error> {
+        serde_json::Deserializer::from_str(string)
+            .into_iter()
+            .collect()
+    }
+
+    fn try_values_from_string(string: &str) -> Option<Result<Vec<ConfigValue>, Self::Error>> {
+        Some(<Self as ConfigDeserializeDocuments<ConfigValue>>::try_configs_from_string(string))
+    }
+}
+
+#[cfg(feature = "json")]
+impl<C> ConfigSerialize<C> for JsonFormat
+where
+    C: Serialize,
+{
+    const NAME: &'static str = "json";
+
+    type Error = serde_json::Error;
+
+    fn try_config_to_string(config: &C) -> Result<String, Self::Error> {
+        serde_json::to_string_pretty(config)
+    }
+}
+
+#[cfg(feature = "toml")]
+pub struct TomlFormat {}
+
+#[cfg(feature = "toml")]
+impl<C> ConfigDeserialize<C> for TomlFormat
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "toml";
+
+    type Error = toml::de::Error;
+
+    fn try_config_from_reader(reader: impl Read) -> Result<C, Self::Error> {
+        // The TOML parser needs the whole document in memory
+        let string = read_to_string::<Self::Error>(reader)?;
+        toml::from_str(&string)
+    }
+
+    fn try_config_from_string(string: &str) -> Result<C, Self::Error> {
+        toml::from_str(string)
+    }
+
+    fn try_value_from_string(string: &str) -> Option<Result<ConfigValue, Self::Error>> {
+        Some(<Self as ConfigDeserialize<ConfigValue>>::try_config_from_string(string))
+    }
+}
+
+#[cfg(feature = "toml")]
+impl<C> ConfigSerialize<C> for TomlFormat
+where
+    C: Serialize,
+{
+    const NAME: &'static str = "toml";
+
+    type Error = toml::ser::Error;
+
+    fn try_config_to_string(config: &C) -> Result<String, Self::Error> {
+        toml::to_string_pretty(config)
+    }
+}
+
+#[cfg(feature = "ron")]
+pub struct RonFormat {}
+
+#[cfg(feature = "ron")]
+impl<C> ConfigDeserialize<C> for RonFormat
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "ron";
+
+    type Error = ron::error::SpannedError;
+
+    fn try_config_from_reader(reader: impl Read) -> Result<C, Self::Error> {
+        ron::de::from_reader(reader)
+    }
+
+    fn try_config_from_string(string: &str) -> Result<C, Self::Error> {
+        ron::from_str(string)
+    }
+
+    fn try_value_from_string(string: &str) -> Option<Result<ConfigValue, Self::Error>> {
+        Some(<Self as ConfigDeserialize<ConfigValue>>::try_config_from_string(string))
+    }
+}
+
+#[cfg(feature = "ron")]
+impl<'de, C> ConfigDeserializeBorrowed<'de, C> for RonFormat
+where
+    C: Deserialize<'de>,
+{
+    const NAME: &'static str = "ron";
+
+    type Error = ron::error::SpannedError;
+
+    fn try_config_from_borrowed_string(string: &'de str) -> Result<C, Self::Error> {
+        ron::from_str(string)
+    }
+}
+
+#[cfg(feature = "ron")]
+impl<C> ConfigSerialize<C> for RonFormat
+where
+    C: Serialize,
+{
+    const NAME: &'static str = "ron";
+
+    type Error = ron::Error;
+
+    fn try_config_to_string(config: &C) -> Result<String, Self::Error> {
+        ron::ser::to_string_pretty(config, ron::ser::PrettyConfig::default())
+    }
+}
+
+#[cfg(feature = "json5")]
+pub struct Json5Format {}
+
+#[cfg(feature = "json5")]
+impl<C> ConfigDeserialize<C> for Json5Format
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "json5";
+
+    type Error = json5::Error;
+
+    fn try_config_from_reader(reader: impl Read) -> Result<C, Self::Error> {
+        // The JSON5 parser needs the whole document in memory
+        let string = read_to_string::<Self::Error>(reader)?;
+        json5::from_str(&string)
+    }
+
+    fn try_config_from_string(string: &str) -> Result<C, Self::Error> {
+        json5::from_str(string)
+    }
+
+    fn try_value_from_string(string: &str) -> Option<Result<ConfigValue, Self::Error>> {
+        Some(<Self as ConfigDeserialize<ConfigValue>>::try_config_from_string(string))
+    }
+}
+
+#[cfg(feature = "json5")]
+impl<'de, C> ConfigDeserializeBorrowed<'de, C> for Json5Format
+where
+    C: Deserialize<'de>,
+{
+    const NAME: &'static str = "json5";
+
+    type Error = json5::Error;
+
+    fn try_config_from_borrowed_string(string: &'de str) -> Result<C, Self::Error> {
+        json5::from_str(string)
+    }
+}
+
+#[cfg(feature = "json5")]
+impl<C> ConfigSerialize<C> for Json5Format
+where
+    C: Serialize,
+{
+    const NAME: &'static str = "json5";
+
+    type Error = json5::Error;
+
+    fn try_config_to_string(config: &C) -> Result<String, Self::Error> {
+        json5::to_string(config)
+    }
+}
+
+#[cfg(feature = "ini")]
+/// INI files. Properties outside of any section map to top-level fields, and
+/// the properties of a section like `[network]` map to the fields of the
+/// nested struct in the field `network`. Dots in section names and keys map to
+/// deeper nesting, so that `[network.proxy]` maps to the field `proxy` of the
+/// struct in the field `network`. Values are coerced to the types of the
+/// fields like those of an [`EnvSource`](super::EnvSource).
+pub struct IniFormat {}
+
+#[cfg(feature = "ini")]
+impl IniFormat {
+    fn text_from_string(string: &str) -> Result<Text, TextError> {
+        let ini = Ini::load_from_str(string)
+            .map_err(Box::from)
+            .context(ParseSnafu)?;
+        let mut text = Text::table();
+        for (section, properties) in ini.iter() {
+            let section_path: Vec<&str> = section
+                .map(|section| section.split('.').collect())
+                .unwrap_or_default();
+            for (key, value) in properties.iter() {
+                let path: Vec<&str> = section_path.iter().copied().chain(key.split('.')).collect();
+                text.insert(&path, Text::Value(value.to_owned()))?;
+            }
+        }
+        Ok(text)
+    }
+}
+
+#[cfg(feature = "ini")]
+impl<C> ConfigDeserialize<C> for IniFormat
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "ini";
+
+    type Error = TextError;
+
+    fn try_config_from_reader(reader: impl Read) -> Result<C, Self::Error> {
+        Self::try_config_from_string(&read_text(reader)?)
+    }
+
+    fn try_config_from_string(string: &str) -> Result<C, Self::Error> {
+        Self::text_from_string(string)?.deserialize_into()
+    }
+
+    fn try_value_from_string(string: &str) -> Option<Result<ConfigValue, Self::Error>> {
+        Some(<Self as ConfigDeserialize<ConfigValue>>::try_config_from_string(string))
+    }
+}
+
+#[cfg(feature = "dotenv")]
+/// Dotenv (`.env`) files. Keys map to fields following the same naming rules
+/// as an [`EnvSource`](super::EnvSource) without a prefix, so that
+/// `LOG_LEVEL=debug` sets the field `log_level` and `NETWORK__TIMEOUT=30` sets
+/// the field `timeout` of the struct in the field `network`. Use a
+/// [`DotenvSource`](super::DotenvSource) to only consider keys with a prefix.
+pub struct DotenvFormat {}
+
+#[cfg(feature = "dotenv")]
+impl DotenvFormat {
+    /// Parse a dotenv file without modifying the environment of the process,
+    /// keeping only the keys with the given prefix, if any.
+    pub(crate) fn text_from_reader(
+        reader: impl Read,
+        prefix: Option<&str>,
+    ) -> Result<Text, TextError> {
+        let vars = dotenvy::from_read_iter(reader)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|dotenv_error| match dotenv_error {
+                dotenvy::Error::Io(source) => TextError::Read { source },
+                dotenv_error => TextError::Parse {
+                    source: Box::new(dotenv_error),
+                },
+            })?;
+        EnvSource::text_from_vars(prefix, vars)
+    }
+}
+
+#[cfg(feature = "dotenv")]
+impl<C> ConfigDeserialize<C> for DotenvFormat
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "dotenv";
+
+    type Error = TextError;
+
+    fn try_config_from_reader(reader: impl Read) -> Result<C, Self::Error> {
+        Self::text_from_reader(reader, None)?.deserialize_into()
+    }
+
+    fn try_config_from_string(string: &str) -> Result<C, Self::Error> {
+        Self::try_config_from_reader(string.as_bytes())
+    }
+
+    fn try_value_from_string(string: &str) -> Option<Result<ConfigValue, Self::Error>> {
+        Some(<Self as ConfigDeserialize<ConfigValue>>::try_config_from_string(string))
+    }
+}
+
+#[cfg(feature = "xml")]
+/// XML documents. The name of the root element is ignored, and attributes and
+/// child elements are treated alike, so that existing configuration structs
+/// can be loaded from either. The mapping rules are as follows:
+/// - `<server port="80"/>` and `<server><port>80</port></server>` both set
+///   the field `port` of the struct in the field `server`.
+/// - An element with only text content maps to a value, which is coerced to
+///   the type of the field like those of an [`EnvSource`](super::EnvSource).
+/// - An element with attributes or child elements maps to a nested struct,
+///   and any text content it also has is available under the key `$text`.
+/// - Repeated child elements map to a list, and so does a single element if
+///   the field is a list.
+///
+/// Namespace prefixes are ignored. Configs are serialized as elements of the
+/// root element `config`, following the same rules.
+pub struct XmlFormat {}
+
+#[cfg(feature = "xml")]
+impl<C> ConfigDeserialize<C> for XmlFormat
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "xml";
+
+    type Error = TextError;
+
+    fn try_config_from_reader(reader: impl Read) -> Result<C, Self::Error> {
+        Self::try_config_from_string(&read_text(reader)?)
+    }
+
+    fn try_config_from_string(string: &str) -> Result<C, Self::Error> {
+        xml::text_from_str(string)?.deserialize_into()
+    }
+
+    fn try_value_from_string(string: &str) -> Option<Result<ConfigValue, Self::Error>> {
+        Some(<Self as ConfigDeserialize<ConfigValue>>::try_config_from_string(string))
+    }
+}
+
+#[cfg(feature = "xml")]
+impl<C> ConfigSerialize<C> for XmlFormat
+where
+    C: Serialize,
+{
+    const NAME: &'static str = "xml";
+
+    type Error = ValueError;
+
+    fn try_config_to_string(config: &C) -> Result<String, Self::Error> {
+        xml::value_to_string(&ConfigValue::from_config(config)?)
+    }
+}
+
+#[cfg(feature = "kdl")]
+/// KDL documents, in either KDL 1 or KDL 2 syntax. Every node maps to the
+/// field named after it, so that existing configuration structs can be loaded
+/// unchanged. The mapping rules are as follows:
+/// - A node with a single argument, like `port 8080`, sets a field, and a
+///   node with several arguments, like `hosts "a" "b"`, sets a list field.
+/// - Properties and children are treated alike, so that `server port=80` and
+///   `server { port 80; }` both set the field `port` of the struct in the
+///   field `server`. Any arguments of such a node are available as a list
+///   under the key `$args`.
+/// - Repeated nodes map to a list.
+/// - `null` leaves a field unset, and type annotations are ignored.
+///
+/// Values are coerced to the types of the fields like those of an
+/// [`EnvSource`](super::EnvSource), so that quoted and unquoted numbers and
+/// booleans are interchangeable. Configs are serialized in KDL 2 syntax,
+/// following the same rules.
+pub struct KdlFormat {}
+
+#[cfg(feature = "kdl")]
+impl<C> ConfigDeserialize<C> for KdlFormat
+where
+    C: DeserializeOwned,
+{
+    const NAME: &'static str = "kdl";
+
+    type Error = TextError;
+
+    fn try_config_from_reader(reader: impl Read) -> Result<C, Self::Error> {
+        Self::try_config_from_string(&read_text(reader)?)
+    }
+
+    fn try_config_from_string(string: &str) -> Result<C, Self::Error> {
+        kdl::text_from_str(string)?.deserialize_into()
+    }
+
+    fn try_value_from_string(string: &str) -> Option<Result<ConfigValue, Self::Error>> {
+        Some(<Self as ConfigDeserialize<ConfigValue>>::try_config_from_string(string))
+    }
+}
+
+#[cfg(feature = "kdl")]
+impl<C> ConfigSerialize<C> for KdlFormat
+where
+    C: Serialize,
+{
+    const NAME: &'static str = "kdl";
+
+    type Error = ValueError;
+
+    fn try_config_to_string(config: &C) -> Result<String, Self::Error> {
+        kdl::value_to_string(&ConfigValue::from_config(config)?)
+    }
+}
+
+/// Read a whole reader into a string for formats whose parsers do not accept
+/// readers, reporting I/O errors through the format's own error type.
+#[cfg(any(feature = "toml", feature = "json5"))]
+fn read_to_string<E>(mut reader: impl Read) -> Result<String, E>
+where
+    E: serde::de::Error,
+{
+    let mut string = String::new();
+    reader.read_to_string(&mut string).map_err(E::custom)?;
+    Ok(string)
+}
+
+// endregion: FORMAT IMPLEMENTATIONS
+
+// region: RUNTIME FORMAT SELECTION
+
+#[cfg(any(
+    feature = "yaml",
+    feature = "json",
+    feature = "toml",
+    feature = "ron",
+    feature = "json5",
+    feature = "ini",
+    feature = "dotenv",
+    feature = "xml",
+    feature = "kdl"
+))]
+/// The formats compiled into the crate, for when the format is only known at
+/// runtime, for example, from the extension of a config file or from a
+/// command-line argument. Each variant dispatches to the format selector of
+/// the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Format {
+    #[cfg(feature = "yaml")]
+    Yaml,
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "toml")]
+    Toml,
+    #[cfg(feature = "ron")]
+    Ron,
+    #[cfg(feature = "json5")]
+    Json5,
+    #[cfg(feature = "ini")]
+    Ini,
+    #[cfg(feature = "dotenv")]
+    Dotenv,
+    #[cfg(feature = "xml")]
+    Xml,
+    #[cfg(feature = "kdl")]
+    Kdl,
+}
+
+#[cfg(any(
+    feature = "yaml",
+    feature = "json",
+    feature = "toml",
+    feature = "ron",
+    feature = "json5",
+    feature = "ini",
+    feature = "dotenv",
+    feature = "xml",
+    feature = "kdl"
+))]
+/// Run an expression with `$selector` aliased to the format selector of the
+/// given [`Format`].
+macro_rules! with_selector {
+    ($format:expr, $selector:ident => $body:expr) => {
+        match $format {
+            #[cfg(feature = "yaml")]
+            Format::Yaml => {
+                type $selector = YamlFormat;
+                $body
+            }
+            #[cfg(feature = "json")]
+            Format::Json => {
+                type $selector = JsonFormat;
+                $body
+            }
+            #[cfg(feature = "toml")]
+            Format::Toml => {
+                type $selector = TomlFormat;
+                $body
+            }
+            #[cfg(feature = "ron")]
+            Format::Ron => {
+                type $selector = RonFormat;
+                $body
+            }
+            #[cfg(feature = "json5")]
+            Format::Json5 => {
+                type $selector = Json5Format;
+                $body
+            }
+            #[cfg(feature = "ini")]
+            Format::Ini => {
+                type $selector = IniFormat;
+                $body
+            }
+            #[cfg(feature = "dotenv")]
+            Format::Dotenv => {
+                type $selector = DotenvFormat;
+                $body
+            }
+            #[cfg(feature = "xml")]
+            Format::Xml => {
+                type $selector = XmlFormat;
+                $body
+            }
+            #[cfg(feature = "kdl")]
+            Format::Kdl => {
+                type $selector = KdlFormat;
+                $body
+            }
+        }
+    };
+}
+
+#[cfg(any(
+    feature = "yaml",
+    feature = "json",
+    feature = "toml",
+    feature = "ron",
+    feature = "json5",
+    feature = "ini",
+    feature = "dotenv",
+    feature = "xml",
+    feature = "kdl"
+))]
+impl Format {
+    /// Every format compiled into the crate.
+    pub const ALL: &'static [Format] = &[
+        #[cfg(feature = "yaml")]
+        Format::Yaml,
+        #[cfg(feature = "json")]
+        Format::Json,
+        #[cfg(feature = "toml")]
+        Format::Toml,
+        #[cfg(feature = "ron")]
+        Format::Ron,
+        #[cfg(feature = "json5")]
+        Format::Json5,
+        #[cfg(feature = "ini")]
+        Format::Ini,
+        #[cfg(feature = "dotenv")]
+        Format::Dotenv,
+        #[cfg(feature = "xml")]
+        Format::Xml,
+        #[cfg(feature = "kdl")]
+        Format::Kdl,
+    ];
+
+    /// The name of the format, which is the same as the `NAME` of its format
+    /// selector.
+    pub fn name(self) -> &'static str {
+        with_selector!(self, D => <D as ConfigDeserialize<IgnoredAny>>::NAME)
+    }
+
+    /// The file extensions of the format, without the leading dot, with the
+    /// preferred extension first.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            #[cfg(feature = "yaml")]
+            Format::Yaml => &["yaml", "yml"],
+            #[cfg(feature = "json")]
+            Format::Json => &["json"],
+            #[cfg(feature = "toml")]
+            Format::Toml => &["toml"],
+            #[cfg(feature = "ron")]
+            Format::Ron => &["ron"],
+            #[cfg(feature = "json5")]
+            Format::Json5 => &["json5"],
+            #[cfg(feature = "ini")]
+            Format::Ini => &["ini"],
+            #[cfg(feature = "dotenv")]
+            Format::Dotenv => &["env"],
+            #[cfg(feature = "xml")]
+            Format::Xml => &["xml"],
+            #[cfg(feature = "kdl")]
+            Format::Kdl => &["kdl"],
+        }
+    }
+
+    /// The format with the given name or file extension, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name() == name || format.extensions().contains(&name.as_str()))
+    }
+
+    /// The format with the given file extension (without the leading dot),
+    /// ignoring case.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let extension = extension.to_ascii_lowercase();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+    }
+
+    /// The format of a config file, guessed from its extension. Files named
+    /// `.env` are dotenv files.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+        #[cfg(feature = "dotenv")]
+        if path.file_name().is_some_and(|name| name == ".env") {
+            return Some(Format::Dotenv);
+        }
+        Self::from_extension(path.extension()?.to_str()?)
+    }
+
+    /// Whether configs can be serialized to the format with
+    /// [`config_to_string()`](Format::config_to_string).
+    pub fn can_serialize(self) -> bool {
+        match self {
+            #[cfg(feature = "yaml")]
+            Format::Yaml => true,
+            #[cfg(feature = "json")]
+            Format::Json => true,
+            #[cfg(feature = "toml")]
+            Format::Toml => true,
+            #[cfg(feature = "ron")]
+            Format::Ron => true,
+            #[cfg(feature = "json5")]
+            Format::Json5 => true,
+            #[cfg(feature = "xml")]
+            Format::Xml => true,
+            #[cfg(feature = "kdl")]
+            Format::Kdl => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Deserialize from a string, like [`Configuration::string()`].
+    ///
+    /// [`Configuration::string()`]: super::Configuration::string
+    pub fn config_from_string<C>(self, string: &str) -> Result<C, Error>
+    where
+        C: DeserializeOwned,
+    {
+        with_selector!(self, D => D::try_config_from_string(string).map_err(Box::from)).context(
+            ParseConfigStringSnafu {
+                string,
+                format: self.name(),
+            },
+        )
+    }
+
+    /// Deserialize from a reader, like [`Configuration::reader()`].
+    ///
+    /// [`Configuration::reader()`]: super::Configuration::reader
+    pub fn config_from_reader<C>(self, reader: impl Read) -> Result<C, Error>
+    where
+        C: DeserializeOwned,
+    {
+        with_selector!(self, D => D::try_config_from_reader(reader).map_err(Box::from)).context(
+            ParseConfigReaderSnafu {
+                format: self.name(),
+            },
+        )
+    }
+
+    /// Deserialize a configuration struct from the file at the given
+    /// filepath, failing if it does not exist, like
+    /// [`Configuration::try_filepath()`]: deprecated keys are migrated, and
+    /// relative [`ConfigRelativePath`]s are resolved against the directory of
+    /// the file.
+    ///
+    /// [`Configuration::try_filepath()`]: super::Configuration::try_filepath
+    /// [`ConfigRelativePath`]: super::ConfigRelativePath
+    pub fn config_from_filepath<C>(self, path: impl AsRef<Path>) -> Result<C, Error>
+    where
+        C: Configuration + DeserializeOwned,
+    {
+        let path = path.as_ref();
+        if !path.exists() {
+            return FindConfigFileSnafu { path }.fail();
+        }
+        let file = File::open(path).context(ReadConfigFileSnafu { path })?;
+        with_selector!(self, D => config_from_file::<C, D>(file, path))
+    }
+
+    /// Deserialize the file at the given filepath as a [`ConfigValue`] tree,
+    /// failing if it does not exist, without any knowledge of the
+    /// configuration struct it belongs to.
+    pub fn value_from_filepath(self, path: impl AsRef<Path>) -> Result<ConfigValue, Error> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return FindConfigFileSnafu { path }.fail();
+        }
+        let file = File::open(path).context(ReadConfigFileSnafu { path })?;
+        let reader = BufReader::new(file);
+        with_selector!(self, D => D::try_config_from_reader(reader).map_err(Box::from)).context(
+            ParseConfigFileSnafu {
+                path,
+                format: self.name(),
+            },
+        )
+    }
+
+    /// A [`FileSource`] for the file at the given filepath in this format,
+    /// for loading configuration structs from files whose format is only
+    /// known at runtime.
+    pub fn file_source<C>(self, path: impl AsRef<Path>) -> Box<dyn ConfigSource<C>>
+    where
+        C: Configuration + DeserializeOwned,
+    {
+        with_selector!(self, D => Box::new(FileSource::<D>::new(path)))
+    }
+
+    /// Serialize to a human-readable string. Only some formats support
+    /// serialization, see [`can_serialize()`](Format::can_serialize).
+    pub fn config_to_string<C>(self, config: &C) -> Result<String, Error>
+    where
+        C: Serialize,
+    {
+        self.serialize(config).context(SerializeConfigSnafu {
+            format: self.name(),
+        })
+    }
+
+    #[cfg_attr(
+        not(any(
+            feature = "yaml",
+            feature = "json",
+            feature = "toml",
+            feature = "ron",
+            feature = "json5",
+            feature = "xml",
+            feature = "kdl"
+        )),
+        allow(unused_variables)
+    )]
+    fn serialize<C>(
+        self,
+        config: &C,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync + 'static>>
+    where
+        C: Serialize,
+    {
+        match self {
+            #[cfg(feature = "yaml")]
+            Format::Yaml => YamlFormat::try_config_to_string(config).map_err(Box::from),
+            #[cfg(feature = "json")]
+            Format::Json => JsonFormat::try_config_to_string(config).map_err(Box::from),
+            #[cfg(feature = "toml")]
+            Format::Toml => TomlFormat::try_config_to_string(config).map_err(Box::from),
+            #[cfg(feature = "ron")]
+            Format::Ron => RonFormat::try_config_to_string(config).map_err(Box::from),
+            #[cfg(feature = "json5")]
+            Format::Json5 => Json5Format::try_config_to_string(config).map_err(Box::from),
+            #[cfg(feature = "xml")]
+            Format::Xml => XmlFormat::try_config_to_string(config).map_err(Box::from),
+            #[cfg(feature = "kdl")]
+            Format::Kdl => KdlFormat::try_config_to_string(config).map_err(Box::from),
+            #[allow(unreachable_patterns)]
+            _ => Err(Box::from("serialization to this format is not supported")),
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "yaml",
+    feature = "json",
+    feature = "toml",
+    feature = "ron",
+    feature = "json5",
+    feature = "ini",
+    feature = "dotenv",
+    feature = "xml",
+    feature = "kdl"
+))]
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// endregion: RUNTIME FORMAT SELECTION
+
+// region: IMPORTS
+
+use std::io::Read;
+
+use super::ConfigValue;
+
+#[cfg(any(feature = "yaml", feature = "json", feature = "ron", feature = "json5"))]
+use serde::de::Deserialize;
+
+// Only needed if at least one format is compiled in
+#[cfg(any(
+    feature = "yaml",
+    feature = "json",
+    feature = "toml",
+    feature = "ron",
+    feature = "json5",
+    feature = "ini",
+    feature = "dotenv",
+    feature = "xml",
+    feature = "kdl"
+))]
+use {
+    super::{
+        deprecated::config_from_file, ConfigSource, Configuration, Error, FileSource,
+        FindConfigFileSnafu, ParseConfigFileSnafu, ParseConfigReaderSnafu, ParseConfigStringSnafu,
+        ReadConfigFileSnafu, SerializeConfigSnafu,
+    },
+    serde::{
+        de::{DeserializeOwned, IgnoredAny},
+        Serialize,
+    },
+    snafu::ResultExt,
+    std::{fmt, fs::File, io::BufReader, path::Path},
+};
+
+#[cfg(feature = "ini")]
+use ini::Ini;
+
+#[cfg(any(feature = "ini", feature = "dotenv"))]
+use super::text::Text;
+
+#[cfg(any(feature = "ini", feature = "dotenv", feature = "xml", feature = "kdl"))]
+use super::text::TextError;
+
+#[cfg(feature = "ini")]
+use super::text::ParseSnafu;
+
+#[cfg(any(feature = "ini", feature = "xml", feature = "kdl"))]
+use super::text::read_text;
+
+#[cfg(feature = "kdl")]
+use super::kdl;
+
+#[cfg(feature = "xml")]
+use super::xml;
+
+#[cfg(feature = "dotenv")]
+use super::EnvSource;
+
+#[cfg(any(feature = "xml", feature = "kdl"))]
+use super::ValueError;
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(all(
+    test,
+    any(feature = "ini", feature = "dotenv", feature = "xml", feature = "kdl")
+))]
+mod tests {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct TestConfig {
+        my_bool: Option<bool>,
+        my_string: Option<String>,
+        my_table: Option<TestTable>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct TestTable {
+        my_port: Option<u16>,
+        my_table: Option<Box<TestTable>>,
+    }
+
+    #[cfg(feature = "ini")]
+    #[test]
+    fn string_ini() {
+        let test_string = r#"
+            my_bool = on
+
+            [my_table]
+            my_port = 8080
+
+            [my_table.my_table]
+            my_port = 8081
+        "#;
+
+        let test_config: TestConfig = IniFormat::try_config_from_string(test_string).unwrap();
+        assert_eq!(
+            test_config,
+            TestConfig {
+                my_bool: Some(true),
+                my_string: None,
+                my_table: Some(TestTable {
+                    my_port: Some(8080),
+                    my_table: Some(Box::new(TestTable {
+                        my_port: Some(8081),
+                        my_table: None,
+                    })),
+                }),
+            }
+        );
+    }
+
+    #[cfg(feature = "dotenv")]
+    #[test]
+    fn string_dotenv() {
+        let test_string = r#"
+            # A comment
+            MY_STRING="Hello World!"
+            MY_TABLE__MY_PORT=8080
+        "#;
+
+        let test_config: TestConfig = DotenvFormat::try_config_from_string(test_string).unwrap();
+        assert_eq!(test_config.my_bool, None);
+        assert_eq!(test_config.my_string, Some(String::from("Hello World!")));
+        assert_eq!(test_config.my_table.unwrap().my_port, Some(8080));
+
+        let error = <DotenvFormat as ConfigDeserialize<TestConfig>>::try_config_from_string(
+            "MY_TABLE__MY_PORT=http",
+        )
+        .unwrap_err();
+        assert!(matches!(error, TextError::Deserialize { .. }));
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn string_xml() {
+        let attributes_string = r#"<config my_bool="yes"><my_table my_port="8080"/></config>"#;
+        let elements_string = r#"
+            <?xml version="1.0"?>
+            <config>
+                <!-- A comment -->
+                <my_bool>yes</my_bool>
+                <my_string><![CDATA[Hello & World!]]></my_string>
+                <my_table>
+                    <my_port>8080</my_port>
+                </my_table>
+            </config>
+        "#;
+
+        let attributes_config: TestConfig =
+            XmlFormat::try_config_from_string(attributes_string).unwrap();
+        let elements_config: TestConfig =
+            XmlFormat::try_config_from_string(elements_string).unwrap();
+        assert_eq!(attributes_config.my_bool, Some(true));
+        assert_eq!(attributes_config.my_table, elements_config.my_table);
+        assert_eq!(
+            elements_config.my_string,
+            Some(String::from("Hello & World!"))
+        );
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct ListConfig {
+            my_ports: Option<Vec<u16>>,
+        }
+
+        let list_config: ListConfig = XmlFormat::try_config_from_string(
+            "<config><my_ports>80</my_ports><my_ports>443</my_ports></config>",
+        )
+        .unwrap();
+        assert_eq!(list_config.my_ports, Some(vec![80, 443]));
+        let list_config: ListConfig =
+            XmlFormat::try_config_from_string("<config><my_ports>80</my_ports></config>").unwrap();
+        assert_eq!(list_config.my_ports, Some(vec![80]));
+
+        let error = <XmlFormat as ConfigDeserialize<TestConfig>>::try_config_from_string(
+            "<config><my_bool>yes</config>",
+        )
+        .unwrap_err();
+        assert!(matches!(error, TextError::Parse { .. }));
+
+        // Empty elements are empty structs, but empty content is a value
+        let empty_config: TestConfig = XmlFormat::try_config_from_string(
+            "<config><my_table/><my_string></my_string></config>",
+        )
+        .unwrap();
+        assert_eq!(
+            empty_config.my_table,
+            Some(TestTable {
+                my_port: None,
+                my_table: None
+            })
+        );
+        assert_eq!(empty_config.my_string, Some(String::new()));
+        let list_config: ListConfig =
+            XmlFormat::try_config_from_string("<config><my_ports></my_ports></config>").unwrap();
+        assert_eq!(list_config.my_ports, Some(vec![]));
+
+        let error = <XmlFormat as ConfigDeserialize<TestConfig>>::try_config_from_string(
+            "<config><my_bool>yes</my_bool></config><config/>",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("more than one root element"));
+    }
+
+    #[cfg(feature = "kdl")]
+    #[test]
+    fn string_kdl() {
+        let properties_string = r#"
+            my_bool #true
+            my_table my_port=8080
+        "#;
+        let children_string = r#"
+            // A comment
+            my_bool true
+            my_string "Hello\tWorld!" /* An inline comment */
+            /-my_string "Discarded"
+            my_table {
+                my_port 0x1F90
+            }
+        "#;
+
+        let properties_config: TestConfig =
+            KdlFormat::try_config_from_string(properties_string).unwrap();
+        let children_config: TestConfig =
+            KdlFormat::try_config_from_string(children_string).unwrap();
+        assert_eq!(properties_config.my_bool, Some(true));
+        assert_eq!(properties_config.my_table, children_config.my_table);
+        assert_eq!(
+            children_config.my_string,
+            Some(String::from("Hello\tWorld!"))
+        );
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct ListConfig {
+            my_ports: Option<Vec<u16>>,
+            my_hosts: Option<Vec<String>>,
+        }
+
+        let list_config: ListConfig = KdlFormat::try_config_from_string(
+            "my_ports 80 443; my_hosts \"localhost\"; my_hosts null",
+        )
+        .unwrap();
+        assert_eq!(list_config.my_ports, Some(vec![80, 443]));
+        assert_eq!(list_config.my_hosts, Some(vec![String::from("localhost")]));
+
+        let error = <KdlFormat as ConfigDeserialize<TestConfig>>::try_config_from_string(
+            "my_table { my_port 8080",
+        )
+        .unwrap_err();
+        assert!(matches!(error, TextError::Parse { .. }));
+    }
+
+    #[cfg(any(feature = "xml", feature = "kdl"))]
+    #[test]
+    fn serialize_xml_kdl() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct SerializeConfig {
+            my_string: Option<String>,
+            my_float: Option<f64>,
+            my_ports: Option<Vec<u16>>,
+            my_hosts: Option<Vec<String>>,
+            my_tables: Option<Vec<SerializeTable>>,
+            my_table: Option<SerializeTable>,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct SerializeTable {
+            my_bool: Option<bool>,
+        }
+
+        let test_config = SerializeConfig {
+            my_string: Some(String::from("<\"Hello\"\tWorld!>")),
+            my_float: Some(f64::INFINITY),
+            my_ports: Some(vec![80, 443]),
+            my_hosts: Some(vec![]),
+            my_tables: Some(vec![
+                SerializeTable {
+                    my_bool: Some(true),
+                },
+                SerializeTable { my_bool: None },
+            ]),
+            my_table: None,
+        };
+
+        #[cfg(feature = "xml")]
+        {
+            let string = XmlFormat::try_config_to_string(&test_config).unwrap();
+            assert!(string.contains("<my_ports>80</my_ports>"));
+            assert!(!string.contains("my_table>"));
+            let config: SerializeConfig = XmlFormat::try_config_from_string(&string).unwrap();
+            assert_eq!(config, test_config);
+            assert!(Format::Xml.can_serialize());
+        }
+
+        #[cfg(feature = "kdl")]
+        {
+            let string = KdlFormat::try_config_to_string(&test_config).unwrap();
+            assert!(string.contains("my_ports 80 443\n"));
+            assert!(string.contains("my_float #inf\n"));
+            let config: SerializeConfig = KdlFormat::try_config_from_string(&string).unwrap();
+            assert_eq!(config, test_config);
+            assert!(Format::Kdl.can_serialize());
+        }
+    }
+
+    // region: IMPORTS
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
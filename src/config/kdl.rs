@@ -0,0 +1,280 @@
+/// Convert a KDL document to a text tree. Every node maps to the field named
+/// after it:
+/// - A node with a single argument, like `port 8080`, maps to a value, and a
+///   node with several arguments, like `hosts "a" "b"`, maps to a list.
+/// - A node with properties or children maps to a nested struct, so that
+///   `server port=80` and `server { port 80; }` both set the field `port` of
+///   the struct in the field `server`. Any arguments it also has are
+///   available as a list under the key `$args`.
+/// - A node without any entries, like `server`, maps to an empty struct.
+/// - Repeated nodes map to a list.
+///
+/// `null` arguments and properties leave their fields unset, and type
+/// annotations are ignored. Documents are read as KDL 2, falling back to
+/// KDL 1.
+pub(crate) fn text_from_str(string: &str) -> Result<Text, TextError> {
+    let document = KdlDocument::parse(string)
+        .map_err(Box::from)
+        .context(ParseSnafu)?;
+    Ok(table_from_nodes(document.nodes()))
+}
+
+fn table_from_nodes(nodes: &[KdlNode]) -> Text {
+    Text::table_from_entries(
+        nodes
+            .iter()
+            .filter_map(|node| Some((node.name().value().to_owned(), text_from_node(node)?))),
+    )
+}
+
+fn text_from_node(node: &KdlNode) -> Option<Text> {
+    let (args, props): (Vec<&KdlEntry>, Vec<&KdlEntry>) = node
+        .entries()
+        .iter()
+        .partition(|entry| entry.name().is_none());
+    let had_args = !args.is_empty();
+    let mut args: Vec<Text> = args
+        .into_iter()
+        .filter_map(|entry| text_from_value(entry.value()))
+        .collect();
+    if props.is_empty() && node.children().is_none() {
+        return match args.len() {
+            0 if had_args => None,
+            0 => Some(Text::table()),
+            1 => args.pop(),
+            _ => Some(Text::List(args)),
+        };
+    }
+
+    // Properties may be repeated, and the last one wins
+    let mut entries: Vec<(String, Text)> = Vec::new();
+    for prop in props {
+        let key = prop.name().map(KdlIdentifier::value).unwrap_or_default();
+        entries.retain(|(other_key, _)| other_key != key);
+        if let Some(value) = text_from_value(prop.value()) {
+            entries.push((key.to_owned(), value));
+        }
+    }
+    if !args.is_empty() {
+        entries.push((String::from("$args"), Text::List(args)));
+    }
+    let mut table = Text::table_from_entries(entries);
+    if let (Text::Table(table), Some(children)) = (&mut table, node.children()) {
+        if let Text::Table(children) = table_from_nodes(children.nodes()) {
+            for (key, value) in children {
+                // Merge children with properties of the same name into lists
+                let value = match table.remove(&key) {
+                    Some(Text::List(mut list)) => {
+                        list.push(value);
+                        Text::List(list)
+                    }
+                    Some(other) => Text::List(vec![other, value]),
+                    None => value,
+                };
+                table.insert(key, value);
+            }
+        }
+    }
+    Some(table)
+}
+
+fn text_from_value(value: &KdlValue) -> Option<Text> {
+    match value {
+        KdlValue::String(string) => Some(Text::Value(string.clone())),
+        KdlValue::Integer(integer) => Some(Text::Value(integer.to_string())),
+        KdlValue::Float(float) => Some(Text::Value(float.to_string())),
+        KdlValue::Bool(value) => Some(Text::Value(value.to_string())),
+        KdlValue::Null => None,
+    }
+}
+
+/// Write a table as a KDL 2 document, the counterpart of [`text_from_str()`]:
+/// - Every entry of the table is written as a node, with its value as the
+///   argument, and a list of values as several arguments. An empty list is
+///   written as an empty string argument, which is read back as a list
+///   without items.
+/// - Tables are written as nodes with children, and the list under the key
+///   `$args` as their arguments.
+/// - Lists of tables are written as repeated nodes.
+///
+/// Null values are left out, so that their fields stay unset.
+pub(crate) fn value_to_string(value: &ConfigValue) -> Result<String, ValueError> {
+    let ConfigValue::Table(table) = value else {
+        return Err(ValueError::custom("only tables can be written as KDL"));
+    };
+    let mut document = nodes_from_table(table)?;
+    document.autoformat();
+    Ok(document.to_string())
+}
+
+fn nodes_from_table(table: &BTreeMap<String, ConfigValue>) -> Result<KdlDocument, ValueError> {
+    let mut document = KdlDocument::new();
+    for (name, value) in table {
+        match value {
+            ConfigValue::Null => {}
+            ConfigValue::List(items) if items.iter().any(is_nested) => {
+                for item in items.iter().filter(|item| !item.is_null()) {
+                    document.nodes_mut().push(node_from_value(name, item)?);
+                }
+            }
+            value => document.nodes_mut().push(node_from_value(name, value)?),
+        }
+    }
+    Ok(document)
+}
+
+fn node_from_value(name: &str, value: &ConfigValue) -> Result<KdlNode, ValueError> {
+    let mut node = KdlNode::new(name);
+    match value {
+        ConfigValue::List(items) if items.is_empty() => {
+            node.push(KdlEntry::new(KdlValue::String(String::new())));
+        }
+        ConfigValue::List(items) => push_args(&mut node, items)?,
+        ConfigValue::Table(table) => {
+            let mut table = table.clone();
+            if let Some(ConfigValue::List(args)) = table.remove("$args") {
+                push_args(&mut node, &args)?;
+            }
+            table.retain(|_, value| !value.is_null());
+            if !table.is_empty() {
+                node.set_children(nodes_from_table(&table)?);
+            }
+        }
+        value => node.push(KdlEntry::new(scalar_to_value(value))),
+    }
+    Ok(node)
+}
+
+fn push_args(node: &mut KdlNode, args: &[ConfigValue]) -> Result<(), ValueError> {
+    for arg in args {
+        if is_nested(arg) {
+            return Err(ValueError::custom(
+                "lists of lists or tables can only be written as KDL in tables",
+            ));
+        }
+        node.push(KdlEntry::new(scalar_to_value(arg)));
+    }
+    Ok(())
+}
+
+fn is_nested(value: &ConfigValue) -> bool {
+    matches!(value, ConfigValue::List(_) | ConfigValue::Table(_))
+}
+
+fn scalar_to_value(value: &ConfigValue) -> KdlValue {
+    match value {
+        ConfigValue::Bool(value) => KdlValue::Bool(*value),
+        ConfigValue::Integer(integer) => KdlValue::Integer((*integer).into()),
+        ConfigValue::Float(float) => KdlValue::Float(*float),
+        ConfigValue::String(string) => KdlValue::String(string.clone()),
+        _ => KdlValue::Null,
+    }
+}
+
+// region: IMPORTS
+
+use std::collections::BTreeMap;
+
+use kdl::{KdlDocument, KdlEntry, KdlIdentifier, KdlNode, KdlValue};
+
+use serde::ser::Error as _;
+
+use snafu::ResultExt;
+
+use super::{
+    text::{ParseSnafu, Text, TextError},
+    ConfigValue, ValueError,
+};
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
+    fn parse(string: &str) -> Text {
+        text_from_str(string).unwrap()
+    }
+
+    fn value(string: &str) -> Text {
+        Text::Value(String::from(string))
+    }
+
+    /// The value of the node `node` in the document.
+    fn node(string: &str) -> Text {
+        parse(string).get(&["node"]).unwrap().clone()
+    }
+
+    #[test]
+    fn nodes() {
+        assert_eq!(node("node 8080"), value("8080"));
+        assert_eq!(
+            node(r#"node "a" "b""#),
+            Text::List(vec![value("a"), value("b")])
+        );
+        assert_eq!(node("node"), Text::table());
+        assert_eq!(parse("server port=80"), parse("server { port 80; }"));
+        assert_eq!(
+            parse(r#"server "main" "backup" port=1 port=2"#),
+            parse(r#"server port=2 { "$args" "main" "backup"; }"#)
+        );
+        assert_eq!(
+            parse("server port=80 { port 8080; }").get(&["server", "port"]),
+            Some(&Text::List(vec![value("80"), value("8080")]))
+        );
+        assert_eq!(
+            parse("host a; host b").get(&["host"]),
+            Some(&Text::List(vec![value("a"), value("b")]))
+        );
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(parse("a #null; b key=#null; c 1"), parse("b; c 1"));
+        assert_eq!(node("node #true"), value("true"));
+        assert_eq!(node("node 0x1F"), value("31"));
+        assert_eq!(node("node 1.5"), value("1.5"));
+        assert_eq!(node("node #-inf"), value("-inf"));
+        assert_eq!(node("node (u16)8080"), value("8080"));
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(parse("a true; b r\"raw\""), parse("a #true; b #\"raw\"#"));
+        assert!(text_from_str("node { child 1").is_err());
+        assert!(text_from_str("node 1 }").is_err());
+    }
+
+    #[test]
+    fn write() {
+        let mut table = ConfigValue::table();
+        table
+            .set("server.$args", ConfigValue::from(vec!["main"]))
+            .unwrap();
+        table.set("server.port", ConfigValue::Integer(80)).unwrap();
+        table
+            .set("my key", ConfigValue::String(String::from("a\"b\n")))
+            .unwrap();
+        table.set("true", ConfigValue::Bool(true)).unwrap();
+        table.set("unset", ConfigValue::Null).unwrap();
+
+        let string = value_to_string(&table).unwrap();
+        assert_eq!(
+            string,
+            "\"my key\" \"a\\\"b\\n\"\nserver main {\n    port 80\n}\n\"true\" #true\n"
+        );
+        assert_eq!(
+            parse(&string),
+            parse("\"my key\" \"a\\\"b\\n\"; server \"main\" port=80; \"true\" true")
+        );
+        assert!(value_to_string(&ConfigValue::Integer(1)).is_err());
+    }
+
+    // region: IMPORTS
+
+    use super::*;
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
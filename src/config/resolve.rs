@@ -0,0 +1,463 @@
+/// To be implemented by configuration structs with a "resolved" companion
+/// struct, which has the same fields (with the same names) but without
+/// `Option` for the fields which must always have a value, so that the rest
+/// of the application does not need to handle unset fields. For example:
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use dapp::config::{Configuration, Finalize};
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(Default, Serialize, Deserialize)]
+/// struct Config {
+///     port: Option<u16>,
+///     log_file: Option<PathBuf>,
+///     # #[serde(skip)]
+///     # loaded: bool,
+/// }
+/// # impl Configuration for Config {
+/// #     fn new() -> Self {
+/// #         Self::default()
+/// #     }
+/// #     fn config(&mut self, other: Self) -> &mut Self {
+/// #         self.port = self.port.or(other.port);
+/// #         self.log_file = self.log_file.take().or(other.log_file);
+/// #         self
+/// #     }
+/// #     fn env(&mut self) -> &mut Self {
+/// #         self
+/// #     }
+/// #     fn set_loaded(&mut self) {
+/// #         self.loaded = true;
+/// #     }
+/// #     fn is_loaded(&self) -> bool {
+/// #         self.loaded
+/// #     }
+/// # }
+///
+/// #[derive(Deserialize)]
+/// struct ResolvedConfig {
+///     port: u16,
+///     log_file: Option<PathBuf>,
+/// }
+///
+/// impl Finalize for Config {
+///     type Resolved = ResolvedConfig;
+/// }
+///
+/// # fn main() -> Result<(), dapp::config::Error> {
+/// let config: ResolvedConfig = Config::new().finalize()?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Finalize: Configuration + Serialize {
+    /// The companion struct with non-optional fields.
+    type Resolved: DeserializeOwned;
+
+    /// Fill any unassigned fields from [`Default`], and convert the
+    /// configuration struct to its resolved companion struct. Unlike
+    /// [`Configuration::fill_defaults()`], this also fills the unassigned
+    /// fields of nested structs which are set. If any fields which are
+    /// required by the resolved struct are still unassigned, a
+    /// [`MissingRequiredField`](Error::MissingRequiredField) error listing
+    /// all of them (as dotted keys) is returned.
+    ///
+    /// Fields are required unless they are optional in the resolved struct,
+    /// so defaults belong in the [`Default`] implementation of the
+    /// configuration struct rather than in `#[serde(default)]` attributes of
+    /// the resolved struct.
+    fn finalize(self) -> Result<Self::Resolved, Error>
+    where
+        Self: Sized,
+    {
+        let mut value = self.to_value().context(ResolveConfigSnafu)?;
+        let defaults = Self::default().to_value().context(ResolveConfigSnafu)?;
+        fill_nulls(&mut value, defaults);
+        resolve(value)
+    }
+}
+
+/// Replace the null values in a tree by those at the same keys in another
+/// tree, recursively.
+fn fill_nulls(value: &mut ConfigValue, other: ConfigValue) {
+    match (value, other) {
+        (value @ ConfigValue::Null, other) => *value = other,
+        (ConfigValue::Table(table), ConfigValue::Table(other)) => {
+            for (key, other_value) in other {
+                match table.get_mut(&key) {
+                    Some(value) => fill_nulls(value, other_value),
+                    None => {
+                        table.insert(key, other_value);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Convert a tree of values to a resolved struct, collecting every missing
+/// field in a single pass instead of stopping at the first one.
+fn resolve<R>(mut value: ConfigValue) -> Result<R, Error>
+where
+    R: DeserializeOwned,
+{
+    // Unset fields are removed, so that the resolved struct reports them as
+    // missing, unless they are optional there too
+    remove_nulls(&mut value);
+    let missing = RefCell::new(Vec::new());
+    let result = R::deserialize(Probe::Value {
+        key: String::new(),
+        value,
+        missing: &missing,
+    });
+    let mut missing = missing.into_inner();
+    match result {
+        Ok(resolved) if missing.is_empty() => Ok(resolved),
+        // Only fields which cannot be made up, like those of flattened
+        // structs, are still reported by the conversion
+        Err(ValueError::MissingField { key }) => {
+            missing.push(key);
+            MissingRequiredFieldSnafu { fields: missing }.fail()
+        }
+        Err(source) if missing.is_empty() => Err(source).context(ResolveConfigSnafu),
+        _ => MissingRequiredFieldSnafu { fields: missing }.fail(),
+    }
+}
+
+fn remove_nulls(value: &mut ConfigValue) {
+    match value {
+        ConfigValue::Table(table) => {
+            table.retain(|_, value| !value.is_null());
+            table.values_mut().for_each(remove_nulls);
+        }
+        ConfigValue::List(list) => list.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+// region: PROBE
+
+/// A tree of values which supplies the missing fields of structs, recording
+/// their dotted keys unless they are optional, and deserializing them to an
+/// empty value of any requested type, so that every missing field is found
+/// in a single pass.
+enum Probe<'a> {
+    Value {
+        key: String,
+        value: ConfigValue,
+        missing: &'a RefCell<Vec<String>>,
+    },
+    /// A field which is not set.
+    Missing {
+        key: String,
+        missing: &'a RefCell<Vec<String>>,
+    },
+    /// A field of a missing struct, which is not reported itself, so that
+    /// only the struct is.
+    Placeholder,
+}
+
+impl<'a> Probe<'a> {
+    fn child_key(key: &str, child: &str) -> String {
+        match key.is_empty() {
+            true => child.to_owned(),
+            false => format!("{}{}{}", key, ConfigValue::SEPARATOR, child),
+        }
+    }
+
+    /// The entries of a table, with the given fields first, in order, so
+    /// that missing fields are reported in the order of their declaration.
+    fn entries(
+        key: &str,
+        mut table: BTreeMap<String, ConfigValue>,
+        fields: &[&str],
+        missing: &'a RefCell<Vec<String>>,
+    ) -> Vec<(String, Probe<'a>)> {
+        let mut entries = Vec::with_capacity(table.len().max(fields.len()));
+        for field in fields {
+            let key = Self::child_key(key, field);
+            let probe = match table.remove(*field) {
+                Some(value) => Probe::Value {
+                    key,
+                    value,
+                    missing,
+                },
+                None => Probe::Missing { key, missing },
+            };
+            entries.push((field.to_string(), probe));
+        }
+        entries.extend(table.into_iter().map(|(child, value)| {
+            let probe = Probe::Value {
+                key: Self::child_key(key, &child),
+                value,
+                missing,
+            };
+            (child, probe)
+        }));
+        entries
+    }
+
+    /// Report missing fields of a table with their full keys. Missing fields
+    /// of nested tables already have full keys, which contain the separator.
+    fn qualify(key: &str, error: ValueError) -> ValueError {
+        match error {
+            ValueError::MissingField { key: field } if !field.contains(ConfigValue::SEPARATOR) => {
+                ValueError::MissingField {
+                    key: Self::child_key(key, &field),
+                }
+            }
+            error => error,
+        }
+    }
+}
+
+macro_rules! deserialize_probe {
+    ($($method:ident($($arg:ident: $type:ty),*) => $placeholder:expr),* $(,)?) => {
+        $(
+            fn $method<V>(self, $($arg: $type,)* visitor: V) -> Result<V::Value, ValueError>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    Probe::Value { key, value: ConfigValue::Table(table), missing } => visitor
+                        .visit_map(MapDeserializer::new(
+                            Probe::entries(&key, table, &[], missing).into_iter(),
+                        ))
+                        .map_err(|error| Probe::qualify(&key, error)),
+                    Probe::Value { value, .. } => value.$method($($arg,)* visitor),
+                    Probe::Missing { key, missing } => {
+                        missing.borrow_mut().push(key);
+                        $(let _ = $arg;)*
+                        #[allow(clippy::redundant_closure_call)]
+                        ($placeholder)(visitor)
+                    }
+                    Probe::Placeholder => {
+                        $(let _ = $arg;)*
+                        #[allow(clippy::redundant_closure_call)]
+                        ($placeholder)(visitor)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Probe<'_> {
+    type Error = ValueError;
+
+    deserialize_probe! {
+        deserialize_any() => |visitor: V| visitor.visit_unit(),
+        deserialize_bool() => |visitor: V| visitor.visit_bool(false),
+        deserialize_i8() => |visitor: V| visitor.visit_i8(0),
+        deserialize_i16() => |visitor: V| visitor.visit_i16(0),
+        deserialize_i32() => |visitor: V| visitor.visit_i32(0),
+        deserialize_i64() => |visitor: V| visitor.visit_i64(0),
+        deserialize_i128() => |visitor: V| visitor.visit_i128(0),
+        deserialize_u8() => |visitor: V| visitor.visit_u8(0),
+        deserialize_u16() => |visitor: V| visitor.visit_u16(0),
+        deserialize_u32() => |visitor: V| visitor.visit_u32(0),
+        deserialize_u64() => |visitor: V| visitor.visit_u64(0),
+        deserialize_u128() => |visitor: V| visitor.visit_u128(0),
+        deserialize_f32() => |visitor: V| visitor.visit_f32(0.0),
+        deserialize_f64() => |visitor: V| visitor.visit_f64(0.0),
+        deserialize_char() => |visitor: V| visitor.visit_char('\0'),
+        deserialize_str() => |visitor: V| visitor.visit_str(""),
+        deserialize_string() => |visitor: V| visitor.visit_string(String::new()),
+        deserialize_bytes() => |visitor: V| visitor.visit_bytes(&[]),
+        deserialize_byte_buf() => |visitor: V| visitor.visit_byte_buf(Vec::new()),
+        deserialize_unit() => |visitor: V| visitor.visit_unit(),
+        deserialize_unit_struct(name: &'static str) => |visitor: V| visitor.visit_unit(),
+        deserialize_seq() => |visitor: V| visitor.visit_seq(SeqDeserializer::new(std::iter::empty::<ConfigValue>())),
+        deserialize_tuple(len: usize) => |visitor: V| visitor.visit_seq(SeqDeserializer::new(std::iter::empty::<ConfigValue>())),
+        deserialize_tuple_struct(name: &'static str, len: usize) => |visitor: V| visitor.visit_seq(SeqDeserializer::new(std::iter::empty::<ConfigValue>())),
+        deserialize_map() => |visitor: V| visitor.visit_map(MapDeserializer::new(std::iter::empty::<(String, ConfigValue)>())),
+        deserialize_identifier() => |visitor: V| visitor.visit_str(""),
+        deserialize_ignored_any() => |visitor: V| visitor.visit_unit(),
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        // Fields which are optional in the resolved struct are never missing
+        match self {
+            probe @ Probe::Value { .. } => visitor.visit_some(probe),
+            Probe::Missing { .. } | Probe::Placeholder => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Probe::Value { value, .. } if !matches!(value, ConfigValue::Table(_)) => {
+                value.deserialize_newtype_struct(name, visitor)
+            }
+            probe => visitor.visit_newtype_struct(probe),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Probe::Value {
+                key,
+                value: ConfigValue::Table(table),
+                missing,
+            } => visitor
+                .visit_map(MapDeserializer::new(
+                    Probe::entries(&key, table, fields, missing).into_iter(),
+                ))
+                .map_err(|error| Probe::qualify(&key, error)),
+            Probe::Value { value, .. } => value.deserialize_struct(name, fields, visitor),
+            Probe::Missing { key, missing } => {
+                missing.borrow_mut().push(key);
+                Probe::Placeholder.deserialize_struct(name, fields, visitor)
+            }
+            Probe::Placeholder => visitor.visit_map(MapDeserializer::new(
+                fields.iter().map(|field| (*field, Probe::Placeholder)),
+            )),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Probe::Value { value, .. } => value.deserialize_enum(name, variants, visitor),
+            Probe::Missing { key, missing } => {
+                missing.borrow_mut().push(key);
+                Probe::Placeholder.deserialize_enum(name, variants, visitor)
+            }
+            // Only unit variants can be made up
+            Probe::Placeholder => match variants.first() {
+                Some(variant) => visitor.visit_enum((*variant).into_deserializer()),
+                None => Err(de::Error::custom(
+                    "an enum without variants cannot be resolved",
+                )),
+            },
+        }
+    }
+}
+
+impl<'de, 'a> IntoDeserializer<'de, ValueError> for Probe<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+// endregion: PROBE
+
+// region: IMPORTS
+
+use std::{cell::RefCell, collections::BTreeMap};
+
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, Deserializer, IntoDeserializer, Visitor,
+    },
+    Serialize,
+};
+
+use snafu::ResultExt;
+
+use super::{
+    ConfigValue, Configuration, Error, MissingRequiredFieldSnafu, ResolveConfigSnafu, ValueError,
+};
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn resolve_missing_fields() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Resolved {
+            name: String,
+            port: u16,
+            tls: Tls,
+            log_file: Option<String>,
+            hosts: Vec<String>,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Tls {
+            certificate: String,
+            key: String,
+        }
+
+        let mut value = ConfigValue::table();
+        value.set("port", ConfigValue::Integer(80)).unwrap();
+        value.set("name", ConfigValue::Null).unwrap();
+
+        // Every missing field is reported at once, and a missing struct as a
+        // whole
+        let error = resolve::<Resolved>(value.clone()).unwrap_err();
+        assert!(
+            matches!(&error, Error::MissingRequiredField { fields } if fields == &["name", "tls", "hosts"])
+        );
+
+        value
+            .set("tls.certificate", ConfigValue::from("cert.pem"))
+            .unwrap();
+        let error = resolve::<Resolved>(value.clone()).unwrap_err();
+        assert!(
+            matches!(&error, Error::MissingRequiredField { fields } if fields == &["name", "tls.key", "hosts"])
+        );
+
+        value.set("tls.key", ConfigValue::from("key.pem")).unwrap();
+        value.set("name", ConfigValue::from("server")).unwrap();
+        value.set("hosts", ConfigValue::from(vec!["a"])).unwrap();
+        assert_eq!(
+            resolve::<Resolved>(value.clone()).unwrap(),
+            Resolved {
+                name: String::from("server"),
+                port: 80,
+                tls: Tls {
+                    certificate: String::from("cert.pem"),
+                    key: String::from("key.pem"),
+                },
+                log_file: None,
+                hosts: vec![String::from("a")],
+            }
+        );
+
+        value.set("port", ConfigValue::from("http")).unwrap();
+        let error = resolve::<Resolved>(value).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Convert);
+    }
+
+    // region: IMPORTS
+
+    use serde::Deserialize;
+
+    use super::{super::ErrorKind, *};
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
@@ -0,0 +1,629 @@
+/// To be implemented by anything a configuration struct can be loaded from,
+/// for example, files, environment variables, a settings table in a database
+/// or a key-value store. Sources are combined in order of precedence by a
+/// [`ConfigLoader`], which also reports where the configuration came from.
+/// The meanings of the generic types are as follows:
+/// - [`C`]: The configuration struct loaded by the source.
+pub trait ConfigSource<C>
+where
+    C: Configuration,
+{
+    /// Load a configuration struct from the source. `Ok(None)` means that the
+    /// source is absent (for example, an optional file that does not exist),
+    /// which is not an error. If the source is present but cannot be read or
+    /// parsed, an error is returned.
+    fn load(&self) -> Result<Option<C>, Error>;
+
+    /// A short, human-readable name for the kind of source (for example,
+    /// `"file"` or `"env"`).
+    fn name(&self) -> String;
+
+    /// Where the source is located (for example, a filepath or a URL), if
+    /// applicable.
+    fn location(&self) -> Option<String> {
+        None
+    }
+}
+
+// region: BUILT-IN SOURCES
+
+#[cfg(feature = "serde")]
+/// A config file in the format selected by [`D`]. A missing file is treated as
+/// an absent source, unless the file is marked as [`required()`].
+///
+/// [`required()`]: FileSource::required
+pub struct FileSource<D> {
+    path: PathBuf,
+    required: bool,
+    security: SecurityPolicy,
+    _format: PhantomData<fn() -> D>,
+}
+
+#[cfg(feature = "serde")]
+/// What a [`FileSource`] does with a config file which could be modified by
+/// users other than the current one, for example, one which is
+/// world-writable or owned by another user. Config files can contain
+/// commands or secrets, so such a file may have been tampered with. Files
+/// are only checked on Unix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum SecurityPolicy {
+    /// Load the file without checking it.
+    #[default]
+    Trust,
+    /// Log a warning explaining how to secure the file, and load it anyway.
+    Warn,
+    /// Fail to load with an [`InsecureConfigFile`] error explaining how to
+    /// secure the file.
+    ///
+    /// [`InsecureConfigFile`]: Error::InsecureConfigFile
+    Refuse,
+}
+
+#[cfg(feature = "serde")]
+impl<D> FileSource<D> {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            required: false,
+            security: SecurityPolicy::default(),
+            _format: PhantomData,
+        }
+    }
+
+    /// Fail to load if the file does not exist, like
+    /// [`Configuration::try_filepath()`].
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Check that the file cannot be modified by users other than the current
+    /// one before loading it (see [`find_insecurities()`]), and warn or fail
+    /// if it can, as set by the policy.
+    ///
+    /// [`find_insecurities()`]: crate::path::find_insecurities
+    pub fn security(mut self, policy: SecurityPolicy) -> Self {
+        self.security = policy;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Open the file, or return `None` if it does not exist. Unless the
+    /// file is trusted, it is opened without following a final symbolic
+    /// link, and the open file is checked, so that the file checked is the
+    /// file read even if the path is replaced in between.
+    #[cfg(unix)]
+    fn open(&self) -> Result<Option<File>, Error> {
+        let context = ReadConfigFileSnafu { path: &self.path };
+        if self.security == SecurityPolicy::Trust {
+            return open_existing(OpenOptions::new().read(true), &self.path).context(context);
+        }
+        let path = match fs::canonicalize(&self.path) {
+            Ok(path) => path,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error).context(context),
+        };
+        let mut options = OpenOptions::new();
+        options
+            .read(true)
+            .custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC);
+        let Some(file) = open_existing(&options, &path).context(context)? else {
+            return Ok(None);
+        };
+        let metadata = file.metadata().context(context)?;
+        let reasons = find_file_insecurities(&path, &metadata).context(context)?;
+        if !reasons.is_empty() {
+            if self.security == SecurityPolicy::Refuse {
+                return InsecureConfigFileSnafu {
+                    path: &self.path,
+                    reasons,
+                }
+                .fail();
+            }
+            for reason in reasons {
+                warn!(path = ?self.path, "insecure config file: {}", reason);
+            }
+        }
+        Ok(Some(file))
+    }
+
+    #[cfg(not(unix))]
+    fn open(&self) -> Result<Option<File>, Error> {
+        open_existing(OpenOptions::new().read(true), &self.path)
+            .context(ReadConfigFileSnafu { path: &self.path })
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Open the file at `path`, or return `None` if it does not exist.
+fn open_existing(options: &OpenOptions, path: &Path) -> io::Result<Option<File>> {
+    match options.open(path) {
+        Ok(file) => Ok(Some(file)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<C, D> ConfigSource<C> for FileSource<D>
+where
+    C: Configuration + DeserializeOwned,
+    D: ConfigDeserialize<C>,
+{
+    fn load(&self) -> Result<Option<C>, Error> {
+        let Some(file) = self.open()? else {
+            ensure!(!self.required, FindConfigFileSnafu { path: &self.path });
+            return Ok(None);
+        };
+        let mut config = C::new();
+        config.config(config_from_file::<C, D>(file, &self.path)?);
+        config.set_loaded();
+        Ok(Some(config))
+    }
+
+    fn name(&self) -> String {
+        format!("{} file", D::NAME)
+    }
+
+    fn location(&self) -> Option<String> {
+        Some(self.path.display().to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Environment variables whose names start with a prefix. The rest of the
+/// name is mapped to a (lowercase) field, and nested fields are separated by
+/// a double underscore. For example, with the prefix `MYAPP`:
+/// - `MYAPP_LOG_LEVEL=debug` sets the field `log_level`
+/// - `MYAPP_NETWORK__TIMEOUT=30` sets the field `timeout` of the nested
+///   struct in the field `network`
+///
+/// Values are coerced to the type of the field, so that `true`, `yes`, `on`
+/// and `1` can all set a boolean field, and comma-separated values can set a
+/// list field.
+pub struct EnvSource {
+    prefix: String,
+}
+
+#[cfg(feature = "serde")]
+impl EnvSource {
+    /// The separator between the components of nested field names.
+    pub const SEPARATOR: &'static str = "__";
+
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// The name of the variable which sets the field at a dotted key.
+    pub fn var_name(&self, key: &str) -> String {
+        let name = key
+            .split(ConfigValue::SEPARATOR)
+            .collect::<Vec<_>>()
+            .join(Self::SEPARATOR)
+            .to_uppercase();
+        format!("{}_{}", self.prefix, name)
+    }
+
+    /// Map variables with the given prefix (or all variables, if there is no
+    /// prefix) to a text tree, following the naming rules above.
+    pub(crate) fn text_from_vars(
+        prefix: Option<&str>,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Text, TextError> {
+        let mut text = Text::table();
+        for (name, value) in vars {
+            let key = match prefix {
+                Some(prefix) => name
+                    .strip_prefix(prefix)
+                    .and_then(|name| name.strip_prefix('_')),
+                None => Some(name.as_str()),
+            };
+            let Some(key) = key else {
+                continue;
+            };
+            let path: Vec<String> = key.split(Self::SEPARATOR).map(str::to_lowercase).collect();
+            text.insert(&path, Text::Value(value))?;
+        }
+        Ok(text)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<C> ConfigSource<C> for EnvSource
+where
+    C: Configuration + DeserializeOwned,
+{
+    fn load(&self) -> Result<Option<C>, Error> {
+        // Variables that are not valid unicode cannot belong to the
+        // configuration, and are skipped
+        let vars = env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        });
+        let context = ParseEnvironmentSnafu {
+            prefix: &self.prefix,
+        };
+        let mut text = Self::text_from_vars(Some(&self.prefix), vars)
+            .map_err(Box::from)
+            .context(context)?;
+        if text.is_empty() {
+            return Ok(None);
+        }
+        migrate_deprecated_keys::<C, _>(&mut text, "the environment", |key| self.var_name(key))?;
+        text.deserialize_into()
+            .map(Some)
+            .map_err(Box::from)
+            .context(context)
+    }
+
+    fn name(&self) -> String {
+        String::from("env")
+    }
+
+    fn location(&self) -> Option<String> {
+        Some(format!("{}_*", self.prefix))
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Overrides from command-line arguments like `-c key=value` or
+/// `--set network.timeout=30` (like `git -c`), to be added to a
+/// [`ConfigLoader`] first, so that they take precedence over every other
+/// source. Keys are dotted keys, and values are coerced to the types of the
+/// fields like those of an [`EnvSource`], so that `on` can set a boolean and
+/// `8080` a number. Values can also be written as literals:
+/// - Lists, like `[1, 2]` or `["a, b", c]`
+/// - Quoted strings, like `"a, b"` or `'[1, 2]'`, to keep commas or brackets
+///   in a string
+///
+/// When the same key is set more than once, the last value wins.
+#[derive(Debug, Clone, Default)]
+pub struct OverrideSource {
+    overrides: Vec<(String, Text)>,
+}
+
+#[cfg(feature = "serde")]
+impl OverrideSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse every argument, like `network.timeout=30`, in order.
+    pub fn parse(arguments: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, Error> {
+        let mut source = Self::new();
+        for argument in arguments {
+            source.push(argument.as_ref())?;
+        }
+        Ok(source)
+    }
+
+    /// Parse an argument, like `network.timeout=30`, and add it after the
+    /// others.
+    pub fn push(&mut self, argument: &str) -> Result<&mut Self, Error> {
+        let context = |reason: &str| InvalidOverrideSnafu {
+            argument,
+            reason: reason.to_owned(),
+        };
+        let (key, literal) = argument
+            .split_once('=')
+            .with_context(|| context("expected `key=value`"))?;
+        let key = key.trim();
+        ensure!(
+            !key.is_empty()
+                && key
+                    .split(ConfigValue::SEPARATOR)
+                    .all(|part| !part.is_empty()),
+            context("the key is not a valid dotted key")
+        );
+        let value = parse_literal(literal).map_err(|reason| context(&reason).build())?;
+        self.overrides.push((key.to_owned(), value));
+        Ok(self)
+    }
+
+    /// The dotted keys which are overridden, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.overrides.iter().map(|(key, _)| key.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<C> ConfigSource<C> for OverrideSource
+where
+    C: Configuration + DeserializeOwned,
+{
+    fn load(&self) -> Result<Option<C>, Error> {
+        if self.overrides.is_empty() {
+            return Ok(None);
+        }
+        let mut text = Text::table();
+        for (key, value) in &self.overrides {
+            let path: Vec<&str> = key.split(ConfigValue::SEPARATOR).collect();
+            text.remove(&path);
+            text.insert(&path, value.clone())
+                .map_err(Box::from)
+                .context(ParseOverridesSnafu)?;
+        }
+        migrate_deprecated_keys::<C, _>(&mut text, "the command-line overrides", str::to_owned)?;
+        text.deserialize_into()
+            .map(Some)
+            .map_err(Box::from)
+            .context(ParseOverridesSnafu)
+    }
+
+    fn name(&self) -> String {
+        String::from("overrides")
+    }
+}
+
+#[cfg(feature = "dotenv")]
+/// A dotenv (`.env`) file, read without modifying the environment of the
+/// process. Keys with a prefix map to fields following the same naming rules
+/// as an [`EnvSource`] with that prefix. Unless a filepath is supplied, the
+/// file is searched for in the current directory and then in its ancestors,
+/// so that the `.env` file at the root of a project is found from any of its
+/// subdirectories. A missing file is treated as an absent source.
+pub struct DotenvSource {
+    prefix: String,
+    path: Option<PathBuf>,
+}
+
+#[cfg(feature = "dotenv")]
+impl DotenvSource {
+    /// The name of the file searched for when no filepath is supplied.
+    pub const FILENAME: &'static str = ".env";
+
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            path: None,
+        }
+    }
+
+    /// Read the file at the given filepath instead of searching for one.
+    pub fn filepath(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// The filepath of the file that will be read, if one exists.
+    pub fn find(&self) -> Option<PathBuf> {
+        match &self.path {
+            Some(path) => Some(path.clone()).filter(|path| path.exists()),
+            None => env::current_dir()
+                .ok()?
+                .ancestors()
+                .map(|directory| directory.join(Self::FILENAME))
+                .first_readable_path(),
+        }
+    }
+}
+
+#[cfg(feature = "dotenv")]
+impl<C> ConfigSource<C> for DotenvSource
+where
+    C: Configuration + DeserializeOwned,
+{
+    fn load(&self) -> Result<Option<C>, Error> {
+        let Some(path) = self.find() else {
+            return Ok(None);
+        };
+        let file = File::open(&path).context(ReadConfigFileSnafu { path: path.clone() })?;
+        let context = ParseConfigFileSnafu {
+            path: &path,
+            format: <DotenvFormat as ConfigDeserialize<C>>::NAME,
+        };
+        let mut text = DotenvFormat::text_from_reader(BufReader::new(file), Some(&self.prefix))
+            .map_err(Box::from)
+            .context(context)?;
+        if text.is_empty() {
+            return Ok(None);
+        }
+        let env_source = EnvSource::new(&self.prefix);
+        let location = format!("the dotenv file at {:?}", path);
+        migrate_deprecated_keys::<C, _>(&mut text, &location, |key| env_source.var_name(key))?;
+        text.deserialize_into()
+            .map(Some)
+            .map_err(Box::from)
+            .context(context)
+    }
+
+    fn name(&self) -> String {
+        String::from("dotenv file")
+    }
+
+    fn location(&self) -> Option<String> {
+        self.find().map(|path| path.display().to_string())
+    }
+}
+
+// endregion: BUILT-IN SOURCES
+
+// region: LOADER
+
+/// Loads a configuration struct from several sources in order of precedence,
+/// and records which sources were present. Sources added first take
+/// precedence over sources added later, just like fields already set on a
+/// configuration struct take precedence over those supplied through
+/// [`Configuration::config()`].
+pub struct ConfigLoader<C>
+where
+    C: Configuration,
+{
+    sources: Vec<Box<dyn ConfigSource<C>>>,
+}
+
+impl<C> ConfigLoader<C>
+where
+    C: Configuration,
+{
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+        }
+    }
+
+    /// Add a source with lower precedence than all the sources added so far.
+    pub fn source(mut self, source: impl ConfigSource<C> + 'static) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// Like [`source()`], but takes a boxed source.
+    ///
+    /// [`source()`]: ConfigLoader::source
+    pub fn boxed_source(mut self, source: Box<dyn ConfigSource<C>>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Load the configuration struct from all the sources. Loading stops at
+    /// the first source which fails.
+    pub fn load(&self) -> Result<C, Error> {
+        self.load_with_provenance().map(|(config, _)| config)
+    }
+
+    /// Like [`load()`], but additionally returns a report of every source and
+    /// whether it was present.
+    ///
+    /// [`load()`]: ConfigLoader::load
+    pub fn load_with_provenance(&self) -> Result<(C, Provenance), Error> {
+        let mut config = C::new();
+        let mut provenance = Provenance::default();
+        for source in &self.sources {
+            let other_config = source.load()?;
+            provenance.records.push(SourceRecord {
+                name: source.name(),
+                location: source.location(),
+                loaded: other_config.is_some(),
+            });
+            config.optional_config(other_config);
+        }
+        Ok((config, provenance))
+    }
+}
+
+impl<C> Default for ConfigLoader<C>
+where
+    C: Configuration,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A report of the sources a configuration struct was loaded from, in order
+/// of precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    records: Vec<SourceRecord>,
+}
+
+impl Provenance {
+    pub fn records(&self) -> &[SourceRecord] {
+        &self.records
+    }
+
+    /// The sources which were present, in order of precedence.
+    pub fn loaded(&self) -> impl Iterator<Item = &SourceRecord> {
+        self.records.iter().filter(|record| record.loaded)
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, record) in self.records.iter().enumerate() {
+            writeln!(f, "{}. {}", index + 1, record)?;
+        }
+        Ok(())
+    }
+}
+
+/// An entry of a [`Provenance`] report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceRecord {
+    pub name: String,
+    pub location: Option<String>,
+    pub loaded: bool,
+}
+
+impl fmt::Display for SourceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(location) = &self.location {
+            write!(f, " ({})", location)?;
+        }
+        if !self.loaded {
+            write!(f, " [absent]")?;
+        }
+        Ok(())
+    }
+}
+
+// endregion: LOADER
+
+// region: IMPORTS
+
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+#[cfg(all(feature = "serde", unix))]
+use std::{fs, os::unix::fs::OpenOptionsExt};
+
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "serde")]
+use snafu::ResultExt;
+
+use super::{Configuration, Error};
+
+#[cfg(feature = "serde")]
+use snafu::{ensure, OptionExt};
+
+#[cfg(feature = "serde")]
+use super::{
+    deprecated::{config_from_file, migrate_deprecated_keys},
+    text::{parse_literal, Text, TextError},
+    ConfigDeserialize, ConfigValue, FindConfigFileSnafu, InvalidOverrideSnafu,
+    ParseEnvironmentSnafu, ParseOverridesSnafu,
+};
+
+#[cfg(feature = "dotenv")]
+use std::io::BufReader;
+
+#[cfg(feature = "dotenv")]
+use super::{DotenvFormat, ParseConfigFileSnafu};
+
+#[cfg(feature = "serde")]
+use super::ReadConfigFileSnafu;
+
+#[cfg(all(feature = "serde", unix))]
+use tracing::warn;
+
+#[cfg(all(feature = "serde", unix))]
+use super::InsecureConfigFileSnafu;
+
+#[cfg(all(feature = "serde", unix))]
+use crate::path::find_file_insecurities;
+
+#[cfg(feature = "dotenv")]
+use crate::path::ValidPaths;
+
+// endregion: IMPORTS
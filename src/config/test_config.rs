@@ -0,0 +1,55 @@
+/// Implement [`Configuration`] for a test config struct whose fields are all
+/// `Option`s, with a `_loaded` field skipped by serde. Fields are merged in
+/// [`config()`](Configuration::config), [`new()`](Configuration::new) is
+/// `Default::default()`, and [`env()`](Configuration::env) reads no
+/// environment variables. Further methods, like
+/// [`deprecated_aliases()`](Configuration::deprecated_aliases), can follow
+/// the fields.
+macro_rules! test_configuration {
+    ($name:ident $(<$lifetime:lifetime>)? { $($field:ident),* $(,)? } $($item:item)*) => {
+        impl$(<$lifetime>)? $crate::config::Configuration for $name$(<$lifetime>)? {
+            fn new() -> Self {
+                Self::default()
+            }
+
+            fn config(&mut self, other: Self) -> &mut Self {
+                $(self.$field = self.$field.take().or(other.$field);)*
+                self.set_loaded();
+                self
+            }
+
+            fn env(&mut self) -> &mut Self {
+                self
+            }
+
+            fn set_loaded(&mut self) {
+                self._loaded = true;
+            }
+
+            fn is_loaded(&self) -> bool {
+                self._loaded
+            }
+
+            $($item)*
+        }
+    };
+}
+
+pub(crate) use test_configuration;
+
+/// The config struct shared by the tests of the config module.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct TestConfig {
+    pub(crate) my_bool: Option<bool>,
+    pub(crate) my_string: Option<String>,
+    #[serde(skip)]
+    pub(crate) _loaded: bool,
+}
+
+test_configuration!(TestConfig { my_bool, my_string });
+
+// region: IMPORTS
+
+use serde::{Deserialize, Serialize};
+
+// endregion: IMPORTS
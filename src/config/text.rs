@@ -0,0 +1,613 @@
+/// An untyped tree of text values keyed by field names, as produced by
+/// sources which have no notion of types, like environment variables. When
+/// deserialized, values are coerced to the type requested by the
+/// configuration struct, so that, for example, the text `true` can be loaded
+/// into an `Option<bool>` field and the text `8080` into an `Option<u16>`
+/// field.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Text {
+    Value(String),
+    List(Vec<Text>),
+    Table(BTreeMap<String, Text>),
+}
+
+impl Text {
+    /// An empty table.
+    pub(crate) fn table() -> Self {
+        Text::Table(BTreeMap::new())
+    }
+
+    /// A table from a sequence of entries, in which the values of repeated
+    /// keys are grouped into lists, as for repeated elements in XML.
+    #[cfg(any(feature = "xml", feature = "kdl"))]
+    pub(crate) fn table_from_entries(entries: impl IntoIterator<Item = (String, Text)>) -> Self {
+        let mut grouped: BTreeMap<String, Vec<Text>> = BTreeMap::new();
+        for (key, value) in entries {
+            grouped.entry(key).or_default().push(value);
+        }
+        Text::Table(
+            grouped
+                .into_iter()
+                .map(|(key, mut values)| match values.len() {
+                    1 => (key, values.remove(0)),
+                    _ => (key, Text::List(values)),
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether this is an empty table.
+    pub(crate) fn is_empty(&self) -> bool {
+        matches!(self, Text::Table(table) if table.is_empty())
+    }
+
+    /// Insert a value at the given path of keys, creating intermediate tables
+    /// as needed. Fails if the path crosses an existing value, or if a table
+    /// already exists at the path.
+    pub(crate) fn insert<K>(&mut self, path: &[K], value: Text) -> Result<(), TextError>
+    where
+        K: AsRef<str>,
+    {
+        let Some((last, parents)) = path.split_last() else {
+            return Err(TextError::custom("empty key"));
+        };
+        let mut table = match self {
+            Text::Table(table) => table,
+            _ => return Err(conflict(&[] as &[K])),
+        };
+        for (index, key) in parents.iter().enumerate() {
+            let entry = table
+                .entry(key.as_ref().to_owned())
+                .or_insert_with(Text::table);
+            table = match entry {
+                Text::Table(table) => table,
+                _ => return Err(conflict(&path[..=index])),
+            };
+        }
+        match table.get(last.as_ref()) {
+            Some(Text::Table(_)) => Err(conflict(path)),
+            _ => {
+                table.insert(last.as_ref().to_owned(), value);
+                Ok(())
+            }
+        }
+    }
+
+    /// The value at the given path of keys, if any.
+    pub(crate) fn get<K>(&self, path: &[K]) -> Option<&Text>
+    where
+        K: AsRef<str>,
+    {
+        path.iter().try_fold(self, |text, key| match text {
+            Text::Table(table) => table.get(key.as_ref()),
+            _ => None,
+        })
+    }
+
+    /// Remove the value at the given path of keys, if any.
+    pub(crate) fn remove<K>(&mut self, path: &[K]) -> Option<Text>
+    where
+        K: AsRef<str>,
+    {
+        let (last, parents) = path.split_last()?;
+        let mut table = match self {
+            Text::Table(table) => table,
+            _ => return None,
+        };
+        for key in parents {
+            table = match table.get_mut(key.as_ref())? {
+                Text::Table(table) => table,
+                _ => return None,
+            };
+        }
+        table.remove(last.as_ref())
+    }
+
+    /// Deserialize a value of any type from the tree.
+    pub(crate) fn deserialize_into<T>(self) -> Result<T, TextError>
+    where
+        T: DeserializeOwned,
+    {
+        T::deserialize(self)
+    }
+
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Text::Value(string) => Unexpected::Str(string),
+            Text::List(_) => Unexpected::Seq,
+            Text::Table(_) => Unexpected::Map,
+        }
+    }
+}
+
+/// Parse a boolean leniently, accepting the spellings commonly used in
+/// environment variables and INI files.
+pub(crate) fn parse_bool(string: &str) -> Option<bool> {
+    match string.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a value written on the command line, like `8080`, `true`, `"a, b"`
+/// or `[1, 2]`. Quoted strings (with `"` or `'`) are unquoted, allowing `\`
+/// escapes, and lists are split into their items. Anything else is kept as
+/// written, to be coerced to the type of the field like any other text.
+pub(crate) fn parse_literal(literal: &str) -> Result<Text, String> {
+    let mut rest = literal.trim();
+    let text = parse_literal_value(&mut rest, true)?;
+    match rest.trim_start() {
+        "" => Ok(text),
+        trailing => Err(format!("unexpected `{}` after the value", trailing)),
+    }
+}
+
+fn parse_literal_value(rest: &mut &str, is_top_level: bool) -> Result<Text, String> {
+    *rest = rest.trim_start();
+    let mut characters = rest.char_indices();
+    match characters.next() {
+        Some((_, '[')) => {
+            *rest = &rest[1..];
+            let mut items = Vec::new();
+            loop {
+                *rest = rest.trim_start();
+                if let Some(after) = rest.strip_prefix(']') {
+                    *rest = after;
+                    return Ok(Text::List(items));
+                }
+                items.push(parse_literal_value(rest, false)?);
+                *rest = rest.trim_start();
+                if let Some(after) = rest.strip_prefix(',') {
+                    *rest = after;
+                } else if !rest.starts_with(']') {
+                    return Err(String::from("expected `,` or `]` in the list"));
+                }
+            }
+        }
+        Some((_, quote @ ('"' | '\''))) => {
+            let mut string = String::new();
+            while let Some((index, character)) = characters.next() {
+                match character {
+                    '\\' => match characters.next() {
+                        Some((_, 'n')) => string.push('\n'),
+                        Some((_, 't')) => string.push('\t'),
+                        Some((_, escaped)) => string.push(escaped),
+                        None => break,
+                    },
+                    character if character == quote => {
+                        *rest = &rest[index + 1..];
+                        return Ok(Text::Value(string));
+                    }
+                    character => string.push(character),
+                }
+            }
+            Err(String::from("the quoted string is not terminated"))
+        }
+        _ if is_top_level => {
+            let text = Text::Value(rest.to_owned());
+            *rest = "";
+            Ok(text)
+        }
+        _ => {
+            let end = rest.find([',', ']']).unwrap_or(rest.len());
+            let item = rest[..end].trim_end();
+            if item.is_empty() {
+                return Err(String::from("the list has an empty item"));
+            }
+            *rest = &rest[end..];
+            Ok(Text::Value(item.to_owned()))
+        }
+    }
+}
+
+fn is_index(key: &str) -> bool {
+    !key.is_empty() && key.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+fn conflict<K>(path: &[K]) -> TextError
+where
+    K: AsRef<str>,
+{
+    let path: Vec<&str> = path.iter().map(AsRef::as_ref).collect();
+    TextError::custom(format!(
+        "the key `{}` is set both as a value and as a table",
+        path.join(".")
+    ))
+}
+
+// region: DESERIALIZER
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident),* $(,)?) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, TextError>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    Text::Value(string) => match string.trim().parse() {
+                        Ok(value) => visitor.$visit(value),
+                        Err(_) => Err(TextError::invalid_value(Unexpected::Str(&string), &visitor)),
+                    },
+                    other => Err(TextError::invalid_type(other.unexpected(), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Text {
+    type Error = TextError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Text::Value(string) => {
+                let trimmed = string.trim();
+                if let Ok(value) = trimmed.parse::<bool>() {
+                    visitor.visit_bool(value)
+                } else if let Ok(value) = trimmed.parse::<i64>() {
+                    visitor.visit_i64(value)
+                } else if let Ok(value) = trimmed.parse::<u64>() {
+                    visitor.visit_u64(value)
+                } else if let Ok(value) = trimmed.parse::<f64>() {
+                    visitor.visit_f64(value)
+                } else {
+                    visitor.visit_string(string)
+                }
+            }
+            Text::List(list) => visitor.visit_seq(SeqDeserializer::new(list.into_iter())),
+            Text::Table(table) => visitor.visit_map(MapDeserializer::new(table.into_iter())),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Text::Value(string) => match parse_bool(&string) {
+                Some(value) => visitor.visit_bool(value),
+                None => Err(TextError::invalid_value(Unexpected::Str(&string), &visitor)),
+            },
+            other => Err(TextError::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Text::Value(string) => visitor.visit_string(string),
+            other => Err(TextError::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Text::Value(string) => visitor.visit_byte_buf(string.into_bytes()),
+            other => Err(TextError::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            // Comma-separated lists, like `PATH`-style environment variables
+            Text::Value(string) => {
+                let items = if string.trim().is_empty() {
+                    Vec::new()
+                } else {
+                    string
+                        .split(',')
+                        .map(|item| Text::Value(item.trim().to_owned()))
+                        .collect()
+                };
+                Text::List(items).deserialize_seq(visitor)
+            }
+            Text::List(list) => visitor.visit_seq(SeqDeserializer::new(list.into_iter())),
+            // Tables with the keys `0`, `1`, ... as produced by indexed keys
+            Text::Table(table) if !table.is_empty() && table.keys().all(|key| is_index(key)) => {
+                let mut items: Vec<(usize, Text)> = table
+                    .into_iter()
+                    .filter_map(|(key, value)| Some((key.parse().ok()?, value)))
+                    .collect();
+                items.sort_by_key(|(index, _)| *index);
+                visitor.visit_seq(SeqDeserializer::new(
+                    items.into_iter().map(|(_, value)| value),
+                ))
+            }
+            // Any other table is a list with a single item, like an element
+            // which happens not to be repeated in XML
+            table => visitor.visit_seq(SeqDeserializer::new(std::iter::once(table))),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Text::Table(table) => visitor.visit_map(MapDeserializer::new(table.into_iter())),
+            other => Err(TextError::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Text::Value(string) => visitor.visit_enum(string.into_deserializer()),
+            Text::Table(table) if table.len() == 1 => visitor.visit_enum(
+                MapAccessDeserializer::new(MapDeserializer::new(table.into_iter())),
+            ),
+            other => Err(TextError::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, TextError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+}
+
+impl<'de> IntoDeserializer<'de, TextError> for Text {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+// endregion: DESERIALIZER
+
+// region: ERRORS
+
+/// The error returned by formats without a notion of types, like INI and
+/// dotenv files, and by environment variables.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum TextError {
+    #[non_exhaustive]
+    #[snafu(display("could not read the config: {source}"), visibility(pub(crate)))]
+    Read { source: std::io::Error },
+
+    #[non_exhaustive]
+    #[snafu(display("{source}"), visibility(pub(crate)))]
+    Parse {
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+
+    #[non_exhaustive]
+    #[snafu(display("{message}"), visibility(pub(crate)))]
+    Deserialize { message: String },
+}
+
+impl serde::de::Error for TextError {
+    fn custom<T>(message: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        TextError::Deserialize {
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Read a whole reader into a string.
+#[cfg(any(feature = "ini", feature = "xml", feature = "kdl"))]
+pub(crate) fn read_text(mut reader: impl Read) -> Result<String, TextError> {
+    let mut string = String::new();
+    reader.read_to_string(&mut string).context(ReadSnafu)?;
+    Ok(string)
+}
+
+// endregion: ERRORS
+
+// region: IMPORTS
+
+use std::{collections::BTreeMap, fmt};
+
+#[cfg(any(feature = "ini", feature = "xml", feature = "kdl"))]
+use std::io::Read;
+
+use serde::de::{
+    value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+    DeserializeOwned, Deserializer, Error as _, IntoDeserializer, Unexpected, Visitor,
+};
+
+use snafu::Snafu;
+
+#[cfg(any(feature = "ini", feature = "xml", feature = "kdl"))]
+use snafu::ResultExt;
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct TestConfig {
+        my_bool: Option<bool>,
+        my_port: Option<u16>,
+        my_list: Option<Vec<String>>,
+        my_table: Option<TestTable>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct TestTable {
+        my_float: Option<f64>,
+    }
+
+    #[test]
+    fn coerce_values() {
+        let mut text = Text::table();
+        text.insert(&["my_bool"], Text::Value("yes".into()))
+            .unwrap();
+        text.insert(&["my_port"], Text::Value("8080".into()))
+            .unwrap();
+        text.insert(&["my_list"], Text::Value("a, b".into()))
+            .unwrap();
+        text.insert(&["my_table", "my_float"], Text::Value("0.5".into()))
+            .unwrap();
+
+        let test_config: TestConfig = text.deserialize_into().unwrap();
+        assert_eq!(
+            test_config,
+            TestConfig {
+                my_bool: Some(true),
+                my_port: Some(8080),
+                my_list: Some(vec![String::from("a"), String::from("b")]),
+                my_table: Some(TestTable {
+                    my_float: Some(0.5)
+                }),
+            }
+        );
+
+        let mut text = Text::table();
+        text.insert(&["my_port"], Text::Value("http".into()))
+            .unwrap();
+        assert!(text.deserialize_into::<TestConfig>().is_err());
+    }
+
+    #[test]
+    fn insert_conflict() {
+        let mut text = Text::table();
+        text.insert(&["a"], Text::Value("1".into())).unwrap();
+        assert!(text.insert(&["a", "b"], Text::Value("2".into())).is_err());
+        text.insert(&["c", "d"], Text::Value("3".into())).unwrap();
+        assert!(text.insert(&["c"], Text::Value("4".into())).is_err());
+    }
+
+    // region: IMPORTS
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
@@ -0,0 +1,649 @@
+// region: DURATION
+
+/// A span of time, written as a sequence of numbers with units, like `30s`,
+/// `1h30m` or `250ms`. The units are `ns`, `us`, `ms`, `s`, `m`, `h`, `d` and
+/// `w`. A bare number (or a number in a format with numbers) is a number of
+/// seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Duration(pub std::time::Duration);
+
+impl Duration {
+    const UNITS: [(&'static str, u128); 8] = [
+        ("ns", 1),
+        ("us", 1_000),
+        ("ms", 1_000_000),
+        ("s", 1_000_000_000),
+        ("m", 60 * 1_000_000_000),
+        ("h", 60 * 60 * 1_000_000_000),
+        ("d", 24 * 60 * 60 * 1_000_000_000),
+        ("w", 7 * 24 * 60 * 60 * 1_000_000_000),
+    ];
+
+    /// The [`std::time::Duration`] this wraps.
+    pub fn get(self) -> std::time::Duration {
+        self.0
+    }
+
+    fn from_nanos(nanos: u128) -> Option<Self> {
+        let seconds = u64::try_from(nanos / 1_000_000_000).ok()?;
+        let nanos = (nanos % 1_000_000_000) as u32;
+        Some(Self(std::time::Duration::new(seconds, nanos)))
+    }
+}
+
+impl FromStr for Duration {
+    type Err = ParseValueError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let context = ParseDurationSnafu { input: string };
+        let trimmed = string.trim();
+        if let Ok(seconds) = trimmed.parse::<u64>() {
+            return Ok(Self(std::time::Duration::from_secs(seconds)));
+        }
+        ensure!(!trimmed.is_empty(), context);
+        let mut nanos: u128 = 0;
+        let mut rest = trimmed;
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|character: char| !character.is_ascii_digit())
+                .context(context)?;
+            let number: u128 = rest[..digits].parse().ok().context(context)?;
+            rest = rest[digits..].trim_start();
+            let unit_length = rest
+                .find(|character: char| !character.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let (_, multiplier) = Self::UNITS
+                .iter()
+                .find(|(unit, _)| *unit == &rest[..unit_length])
+                .context(context)?;
+            nanos = number
+                .checked_mul(*multiplier)
+                .and_then(|part| nanos.checked_add(part))
+                .context(context)?;
+            rest = rest[unit_length..].trim_start();
+        }
+        Self::from_nanos(nanos).context(context)
+    }
+}
+
+impl fmt::Display for Duration {
+    /// Writes the largest units first, like `1h30m`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut nanos = self.0.as_nanos();
+        if nanos == 0 {
+            return f.write_str("0s");
+        }
+        // Weeks are left out, since `2w` is rarely clearer than `14d`
+        for (unit, multiplier) in Self::UNITS.iter().rev().skip(1) {
+            if nanos >= *multiplier {
+                write!(f, "{}{}", nanos / multiplier, unit)?;
+                nanos %= multiplier;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<std::time::Duration> for Duration {
+    fn from(duration: std::time::Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<Duration> for std::time::Duration {
+    fn from(duration: Duration) -> Self {
+        duration.0
+    }
+}
+
+// endregion: DURATION
+
+// region: BYTE SIZE
+
+/// A number of bytes, written as a number with an optional unit, like
+/// `512MiB`, `1.5GB` or `4096`. Units with an `i` (`KiB`, `MiB`, `GiB`,
+/// `TiB`, `PiB`) are powers of 1024, and the others (`kB`, `MB`, `GB`, `TB`,
+/// `PB`) are powers of 1000. Units are case-insensitive, and the trailing `B`
+/// may be left out, so `512m` is `512MB`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    const UNITS: [(&'static str, u64); 11] = [
+        ("", 1),
+        ("k", 1_000),
+        ("ki", 1 << 10),
+        ("m", 1_000_000),
+        ("mi", 1 << 20),
+        ("g", 1_000_000_000),
+        ("gi", 1 << 30),
+        ("t", 1_000_000_000_000),
+        ("ti", 1 << 40),
+        ("p", 1_000_000_000_000_000),
+        ("pi", 1 << 50),
+    ];
+
+    /// The number of bytes.
+    pub fn bytes(self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = ParseValueError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let context = ParseByteSizeSnafu { input: string };
+        let trimmed = string.trim();
+        let split = trimmed
+            .find(|character: char| !(character.is_ascii_digit() || character == '.'))
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+        let unit = unit.trim_start().to_ascii_lowercase();
+        let unit = unit.strip_suffix('b').unwrap_or(&unit);
+        let (_, multiplier) = Self::UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .context(context)?;
+        if let Ok(number) = number.parse::<u64>() {
+            return number.checked_mul(*multiplier).map(Self).context(context);
+        }
+        let number: f64 = number.parse().ok().context(context)?;
+        let bytes = number * *multiplier as f64;
+        ensure!(bytes.is_finite() && bytes <= u64::MAX as f64, context);
+        Ok(Self(bytes.round() as u64))
+    }
+}
+
+impl fmt::Display for ByteSize {
+    /// Writes the largest binary unit which the size is a whole multiple of,
+    /// like `512MiB`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = [
+            ("PiB", 1 << 50),
+            ("TiB", 1 << 40),
+            ("GiB", 1 << 30),
+            ("MiB", 1 << 20),
+            ("KiB", 1 << 10),
+        ];
+        for (unit, multiplier) in units {
+            if self.0 != 0 && self.0 % multiplier == 0 {
+                return write!(f, "{}{}", self.0 / multiplier, unit);
+            }
+        }
+        write!(f, "{}B", self.0)
+    }
+}
+
+impl From<u64> for ByteSize {
+    fn from(bytes: u64) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> Self {
+        size.0
+    }
+}
+
+// endregion: BYTE SIZE
+
+// region: URL
+
+/// A URL-like string with a scheme, like `https://example.com:8443/api` or
+/// `unix:/run/app.sock`. Only the overall shape is checked, so that
+/// configuration mistakes (like a missing scheme) are caught when the config
+/// is loaded; the URL is otherwise kept as written.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Url(String);
+
+impl Url {
+    /// The URL as written.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The scheme, like `https`.
+    pub fn scheme(&self) -> &str {
+        let (scheme, _) = self.0.split_once(':').unwrap_or_default();
+        scheme
+    }
+
+    /// The authority (like `user@example.com:8443`), if the URL has one.
+    fn authority(&self) -> Option<&str> {
+        let (_, rest) = self.0.split_once(':')?;
+        let rest = rest.strip_prefix("//")?;
+        let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        Some(&rest[..end])
+    }
+
+    /// The host, like `example.com`, if the URL has one. IPv6 addresses keep
+    /// their brackets.
+    pub fn host(&self) -> Option<&str> {
+        let authority = self.authority()?;
+        let host_port = authority
+            .rsplit_once('@')
+            .map_or(authority, |(_, host_port)| host_port);
+        let host = match host_port.rfind(':') {
+            Some(index) if !host_port[index..].contains(']') => &host_port[..index],
+            _ => host_port,
+        };
+        Some(host).filter(|host| !host.is_empty())
+    }
+
+    /// The port, if the URL has an explicit one.
+    pub fn port(&self) -> Option<u16> {
+        let authority = self.authority()?;
+        let (_, port) = authority.rsplit_once(':')?;
+        port.parse().ok()
+    }
+
+    /// The path, without the query and fragment. It is empty if the URL has
+    /// none.
+    pub fn path(&self) -> &str {
+        let (_, rest) = self.0.split_once(':').unwrap_or_default();
+        let rest = match rest.strip_prefix("//") {
+            Some(rest) => &rest[rest.find(['/', '?', '#']).unwrap_or(rest.len())..],
+            None => rest,
+        };
+        let end = rest.find(['?', '#']).unwrap_or(rest.len());
+        &rest[..end]
+    }
+}
+
+impl FromStr for Url {
+    type Err = ParseValueError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let context = ParseUrlSnafu { input: string };
+        let (scheme, rest) = string.split_once(':').context(context)?;
+        let mut scheme_characters = scheme.chars();
+        ensure!(
+            scheme_characters
+                .next()
+                .is_some_and(|character| character.is_ascii_alphabetic())
+                && scheme_characters.all(|character| {
+                    character.is_ascii_alphanumeric() || matches!(character, '+' | '-' | '.')
+                })
+                && !rest.is_empty()
+                && !string.chars().any(char::is_whitespace),
+            context
+        );
+        let url = Self(string.to_owned());
+        ensure!(url.authority().is_none() || url.host().is_some(), context);
+        Ok(url)
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Url {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// endregion: URL
+
+// region: CONFIG RELATIVE PATH
+
+thread_local! {
+    /// The directory of the config file being loaded by
+    /// [`Configuration::filepath()`], if any.
+    static BASE_DIRECTORY: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Run a closure which deserializes the config file at `config_filepath`, so
+/// that the [`ConfigRelativePath`]s in it are resolved against the directory
+/// of the file.
+pub(crate) fn with_config_filepath<T>(config_filepath: &Path, f: impl FnOnce() -> T) -> T {
+    let base_directory = config_filepath.parent().map(Path::to_owned);
+    let previous = BASE_DIRECTORY.with(|cell| cell.replace(base_directory));
+    let result = f();
+    BASE_DIRECTORY.with(|cell| cell.replace(previous));
+    result
+}
+
+/// A path which, when it is relative and loaded from a config file with
+/// [`Configuration::filepath()`] (or any of its variants, or a
+/// [`FileSource`]), is relative to the directory of that config file rather
+/// than to the current directory. So `data = "./data"` in
+/// `/etc/myapp/config.toml` refers to `/etc/myapp/data`. Paths loaded from
+/// other sources, like environment variables, are relative to the current
+/// directory as usual.
+///
+/// [`FileSource`]: super::FileSource
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConfigRelativePath {
+    path: PathBuf,
+    base_directory: Option<PathBuf>,
+}
+
+impl ConfigRelativePath {
+    /// A path which is not relative to any config file.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            base_directory: None,
+        }
+    }
+
+    /// The path as written in the config.
+    pub fn as_written(&self) -> &Path {
+        &self.path
+    }
+
+    /// The directory of the config file the path was loaded from, if any.
+    pub fn base_directory(&self) -> Option<&Path> {
+        self.base_directory.as_deref()
+    }
+
+    /// The path, joined to the directory of the config file it was loaded
+    /// from if it is relative.
+    pub fn resolved(&self) -> PathBuf {
+        match &self.base_directory {
+            Some(base_directory) => base_directory.join(&self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
+impl From<PathBuf> for ConfigRelativePath {
+    fn from(path: PathBuf) -> Self {
+        Self::new(path)
+    }
+}
+
+impl Serialize for ConfigRelativePath {
+    /// Serializes the path as written, so that it stays relative to the
+    /// config file. Binary formats, like the MessagePack of a `ConfigCache`,
+    /// keep the base directory too, so that the path is resolved the same
+    /// when it is read back.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match serializer.is_human_readable() {
+            true => self.path.serialize(serializer),
+            false => (&self.path, &self.base_directory).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ConfigRelativePath {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if !deserializer.is_human_readable() {
+            let (path, base_directory) = Deserialize::deserialize(deserializer)?;
+            return Ok(Self {
+                path,
+                base_directory,
+            });
+        }
+        let path = PathBuf::deserialize(deserializer)?;
+        let base_directory = match path.is_relative() {
+            true => BASE_DIRECTORY.with(|cell| cell.borrow().clone()),
+            false => None,
+        };
+        Ok(Self {
+            path,
+            base_directory,
+        })
+    }
+}
+
+// endregion: CONFIG RELATIVE PATH
+
+// region: SERDE
+
+/// Serialize and deserialize the text-based value types through [`FromStr`]
+/// and [`Display`](fmt::Display). Numbers are accepted where the type has a
+/// natural unit.
+macro_rules! serde_from_str {
+    ($($type:ident: $expecting:literal $(, $number:expr)?;)*) => {
+        $(
+            impl Serialize for $type {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $type {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: Deserializer<'de>,
+                {
+                    struct ValueVisitor;
+
+                    impl<'de> Visitor<'de> for ValueVisitor {
+                        type Value = $type;
+
+                        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                            f.write_str($expecting)
+                        }
+
+                        fn visit_str<E>(self, string: &str) -> Result<Self::Value, E>
+                        where
+                            E: de::Error,
+                        {
+                            string.parse().map_err(E::custom)
+                        }
+
+                        $(
+                            fn visit_u64<E>(self, number: u64) -> Result<Self::Value, E>
+                            where
+                                E: de::Error,
+                            {
+                                #[allow(clippy::redundant_closure_call)]
+                                Ok(($number)(number))
+                            }
+
+                            fn visit_i64<E>(self, number: i64) -> Result<Self::Value, E>
+                            where
+                                E: de::Error,
+                            {
+                                let number = u64::try_from(number).map_err(|_| {
+                                    E::invalid_value(Unexpected::Signed(number), &self)
+                                })?;
+                                self.visit_u64(number)
+                            }
+                        )?
+                    }
+
+                    deserializer.deserialize_any(ValueVisitor)
+                }
+            }
+        )*
+    };
+}
+
+serde_from_str! {
+    Duration: "a duration like \"30s\" or a number of seconds",
+        |seconds| Duration(std::time::Duration::from_secs(seconds));
+    ByteSize: "a size like \"512MiB\" or a number of bytes", ByteSize;
+    Url: "a URL like \"https://example.com\"";
+}
+
+// endregion: SERDE
+
+// region: ERRORS
+
+/// The error returned when text cannot be parsed into one of the value types
+/// of this module.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum ParseValueError {
+    #[non_exhaustive]
+    #[snafu(
+        display("{:?} is not a duration like \"30s\", \"1h30m\" or \"250ms\"", input),
+        visibility(pub(crate))
+    )]
+    ParseDuration { input: String },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("{:?} is not a size like \"512MiB\", \"1.5GB\" or \"4096\"", input),
+        visibility(pub(crate))
+    )]
+    ParseByteSize { input: String },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("{:?} is not a URL like \"https://example.com\"", input),
+        visibility(pub(crate))
+    )]
+    ParseUrl { input: String },
+}
+
+// endregion: ERRORS
+
+// region: IMPORTS
+
+use std::{
+    cell::RefCell,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{
+    de::{self, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use snafu::{ensure, OptionExt, Snafu};
+
+#[cfg(doc)]
+use super::Configuration;
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(all(test, feature = "yaml", feature = "toml"))]
+mod tests {
+    #[derive(Debug, Deserialize, Serialize)]
+    struct TypedConfig {
+        timeout: Duration,
+        cache_size: ByteSize,
+        endpoint: Url,
+        data: ConfigRelativePath,
+    }
+
+    #[test]
+    fn parse_values() {
+        let duration: Duration = "1h 30m".parse().unwrap();
+        assert_eq!(duration.get(), std::time::Duration::from_secs(5400));
+        assert_eq!(duration.to_string(), "1h30m");
+        assert_eq!("250ms".parse::<Duration>().unwrap().to_string(), "250ms");
+        assert!("30 seconds".parse::<Duration>().is_err());
+
+        let size: ByteSize = "512MiB".parse().unwrap();
+        assert_eq!(size.bytes(), 512 << 20);
+        assert_eq!(size.to_string(), "512MiB");
+        assert_eq!("1.5GB".parse::<ByteSize>().unwrap().bytes(), 1_500_000_000);
+        assert_eq!("4096".parse::<ByteSize>().unwrap().to_string(), "4KiB");
+        assert!("12 parsecs".parse::<ByteSize>().is_err());
+
+        let url: Url = "https://user@example.com:8443/api?q=1".parse().unwrap();
+        assert_eq!(url.scheme(), "https");
+        assert_eq!(url.host(), Some("example.com"));
+        assert_eq!(url.port(), Some(8443));
+        assert_eq!(url.path(), "/api");
+        assert_eq!(
+            "unix:/run/app.sock".parse::<Url>().unwrap().path(),
+            "/run/app.sock"
+        );
+        assert!("example.com".parse::<Url>().is_err());
+        assert!("http://".parse::<Url>().is_err());
+    }
+
+    #[test]
+    fn deserialize_across_formats() {
+        let yaml =
+            "timeout: 30s\ncache_size: 512MiB\nendpoint: https://example.com\ndata: ./data\n";
+        let toml = "timeout = 30\ncache_size = 536870912\nendpoint = \"https://example.com\"\ndata = \"./data\"\n";
+        let from_yaml: TypedConfig = YamlFormat::try_config_from_string(yaml).unwrap();
+        let from_toml: TypedConfig = TomlFormat::try_config_from_string(toml).unwrap();
+        let from_value: TypedConfig =
+            <YamlFormat as ConfigDeserialize<ConfigValue>>::try_config_from_string(yaml)
+                .unwrap()
+                .into_config()
+                .unwrap();
+        for config in [&from_yaml, &from_toml, &from_value] {
+            assert_eq!(config.timeout.get(), std::time::Duration::from_secs(30));
+            assert_eq!(config.cache_size.bytes(), 512 << 20);
+            assert_eq!(config.endpoint.host(), Some("example.com"));
+            assert_eq!(config.data.resolved(), Path::new("./data"));
+        }
+        assert_eq!(YamlFormat::try_config_to_string(&from_toml).unwrap(), yaml);
+    }
+
+    #[test]
+    fn config_relative_path() {
+        #[derive(Debug, Default, Deserialize)]
+        struct PathConfig {
+            data: Option<ConfigRelativePath>,
+            log_file: Option<ConfigRelativePath>,
+            #[serde(skip)]
+            _loaded: bool,
+        }
+
+        test_configuration!(PathConfig { data, log_file });
+
+        let directory = tempfile::tempdir().unwrap();
+        let config_filepath = directory.path().join("app.yaml");
+        fs::write(
+            &config_filepath,
+            "data: ./data\nlog_file: /var/log/app.log\n",
+        )
+        .unwrap();
+
+        let mut path_config = PathConfig::new();
+        path_config
+            .filepath::<YamlFormat>(&config_filepath)
+            .unwrap();
+        let data = path_config.data.unwrap();
+        assert_eq!(data.as_written(), Path::new("./data"));
+        assert_eq!(data.resolved(), directory.path().join("./data"));
+        assert_eq!(
+            path_config.log_file.unwrap().resolved(),
+            Path::new("/var/log/app.log")
+        );
+        let path_config: PathConfig = Format::Yaml.config_from_filepath(&config_filepath).unwrap();
+        assert_eq!(
+            path_config.data.unwrap().resolved(),
+            directory.path().join("./data")
+        );
+
+        let mut path_config = PathConfig::new();
+        path_config.string::<YamlFormat>("data: ./data").unwrap();
+        assert_eq!(path_config.data.unwrap().resolved(), Path::new("./data"));
+    }
+
+    // region: IMPORTS
+
+    use std::fs;
+
+    use super::{
+        super::{
+            test_config::test_configuration, ConfigDeserialize, ConfigSerialize, ConfigValue,
+            Configuration, Format, TomlFormat, YamlFormat,
+        },
+        *,
+    };
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
@@ -0,0 +1,1092 @@
+/// An untyped tree of values, for runtime access to the fields of a
+/// configuration struct by a dotted key, like `network.timeout`, for example,
+/// in `config get` and `config set` subcommands. Any type which implements
+/// serde's `Serialize` can be converted to a value with
+/// [`from_config()`](ConfigValue::from_config), and any type which implements
+/// `Deserialize` can be converted back with
+/// [`into_config()`](ConfigValue::into_config).
+///
+/// Unset (`None`) fields are represented by [`ConfigValue::Null`], so that
+/// every field of a configuration struct has a key, whether or not it is set.
+/// When converted to a configuration struct, values are coerced to the types
+/// of the fields like those of an [`EnvSource`](super::EnvSource), so that,
+/// for example, the string `"30"` can set an `Option<u16>` field, and the
+/// values of formats without a notion of types survive a round trip.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<ConfigValue>),
+    Table(BTreeMap<String, ConfigValue>),
+}
+
+impl ConfigValue {
+    /// The separator between the components of dotted keys.
+    pub const SEPARATOR: char = '.';
+
+    /// An empty table.
+    pub fn table() -> Self {
+        ConfigValue::Table(BTreeMap::new())
+    }
+
+    /// Convert a configuration struct (or any other serializable value) to a
+    /// value.
+    pub fn from_config<C>(config: &C) -> Result<Self, ValueError>
+    where
+        C: Serialize + ?Sized,
+    {
+        config.serialize(ValueSerializer)
+    }
+
+    /// Convert the value to a configuration struct (or any other
+    /// deserializable value).
+    pub fn into_config<C>(self) -> Result<C, ValueError>
+    where
+        C: DeserializeOwned,
+    {
+        C::deserialize(self)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, ConfigValue::Null)
+    }
+
+    /// The value at a dotted key, if any. Numeric components of the key index
+    /// into lists, so that `hosts.0` is the first item of the list in the
+    /// field `hosts`.
+    pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        key.split(Self::SEPARATOR)
+            .try_fold(self, |value, component| match value {
+                ConfigValue::Table(table) => table.get(component),
+                ConfigValue::List(list) => list.get(component.parse::<usize>().ok()?),
+                _ => None,
+            })
+    }
+
+    /// Like [`get()`](ConfigValue::get), but returns a mutable reference.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut ConfigValue> {
+        key.split(Self::SEPARATOR)
+            .try_fold(self, |value, component| match value {
+                ConfigValue::Table(table) => table.get_mut(component),
+                ConfigValue::List(list) => list.get_mut(component.parse::<usize>().ok()?),
+                _ => None,
+            })
+    }
+
+    /// Remove the entry at a dotted key from its table, returning its value.
+    /// Items of lists cannot be removed.
+    pub fn remove(&mut self, key: &str) -> Option<ConfigValue> {
+        let (parent, last) = match key.rsplit_once(Self::SEPARATOR) {
+            Some((parent, last)) => (self.get_mut(parent)?, last),
+            None => (self, key),
+        };
+        match parent {
+            ConfigValue::Table(table) => table.remove(last),
+            _ => None,
+        }
+    }
+
+    /// Set the value at a dotted key, creating any missing tables along the
+    /// way (null values count as missing). The previous value, if any, is
+    /// returned. This does not check the value against any configuration
+    /// struct, see [`Configuration::set_value()`] for that.
+    ///
+    /// [`Configuration::set_value()`]: super::Configuration::set_value
+    pub fn set(&mut self, key: &str, value: ConfigValue) -> Result<Option<Self>, ValueError> {
+        let components: Vec<&str> = key.split(Self::SEPARATOR).collect();
+        ensure!(
+            components.iter().all(|component| !component.is_empty()),
+            InvalidKeySnafu { key }
+        );
+        let (last, parents) = components
+            .split_last()
+            .expect("splitting yields at least one component");
+        let mut current = self;
+        for (index, component) in parents.iter().enumerate() {
+            if current.is_null() {
+                *current = Self::table();
+            }
+            current = match current {
+                ConfigValue::Table(table) => table
+                    .entry(component.to_string())
+                    .or_insert(ConfigValue::Null),
+                ConfigValue::List(list) => component
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| list.get_mut(index))
+                    .context(NotATableSnafu {
+                        key: components[..=index].join("."),
+                    })?,
+                _ => {
+                    return NotATableSnafu {
+                        key: components[..index].join("."),
+                    }
+                    .fail()
+                }
+            };
+        }
+        if current.is_null() {
+            *current = Self::table();
+        }
+        match current {
+            ConfigValue::Table(table) => Ok(table.insert(last.to_string(), value)),
+            ConfigValue::List(list) => {
+                let item = last
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| list.get_mut(index))
+                    .context(NotATableSnafu { key })?;
+                Ok(Some(std::mem::replace(item, value)))
+            }
+            _ => NotATableSnafu {
+                key: parents.join("."),
+            }
+            .fail(),
+        }
+    }
+
+    /// Every value which is not a table, with its dotted key, in order of
+    /// keys. Lists are listed as a whole, and so are empty tables.
+    pub fn entries(&self) -> Vec<(String, &ConfigValue)> {
+        let mut entries = Vec::new();
+        self.collect_entries(String::new(), &mut entries);
+        entries
+    }
+
+    fn collect_entries<'v>(&'v self, key: String, entries: &mut Vec<(String, &'v ConfigValue)>) {
+        match self {
+            ConfigValue::Table(table) if !table.is_empty() => {
+                for (component, value) in table {
+                    let key = match key.is_empty() {
+                        true => component.clone(),
+                        false => format!("{}{}{}", key, Self::SEPARATOR, component),
+                    };
+                    value.collect_entries(key, entries);
+                }
+            }
+            value => entries.push((key, value)),
+        }
+    }
+
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            ConfigValue::Null => Unexpected::Unit,
+            ConfigValue::Bool(value) => Unexpected::Bool(*value),
+            ConfigValue::Integer(value) => Unexpected::Signed(*value),
+            ConfigValue::Float(value) => Unexpected::Float(*value),
+            ConfigValue::String(string) => Unexpected::Str(string),
+            ConfigValue::List(_) => Unexpected::Seq,
+            ConfigValue::Table(_) => Unexpected::Map,
+        }
+    }
+}
+
+impl Default for ConfigValue {
+    fn default() -> Self {
+        Self::table()
+    }
+}
+
+/// Values are displayed as literals: `null`, `true`, `8080`, `0.5`,
+/// `"text"`, `[1, 2]` and `{ key = "value" }`.
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigValue::Null => f.write_str("null"),
+            ConfigValue::Bool(value) => write!(f, "{value}"),
+            ConfigValue::Integer(value) => write!(f, "{value}"),
+            ConfigValue::Float(value) => write!(f, "{value:?}"),
+            ConfigValue::String(string) => write!(f, "{string:?}"),
+            ConfigValue::List(list) => {
+                f.write_str("[")?;
+                for (index, item) in list.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str("]")
+            }
+            ConfigValue::Table(table) if table.is_empty() => f.write_str("{}"),
+            ConfigValue::Table(table) => {
+                f.write_str("{ ")?;
+                for (index, (key, value)) in table.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key} = {value}")?;
+                }
+                f.write_str(" }")
+            }
+        }
+    }
+}
+
+// region: CONVERSIONS
+
+macro_rules! from_values {
+    ($($variant:ident($($type:ty),*)),* $(,)?) => {
+        $($(
+            impl From<$type> for ConfigValue {
+                fn from(value: $type) -> Self {
+                    ConfigValue::$variant(value.into())
+                }
+            }
+        )*)*
+    };
+}
+
+from_values! {
+    Bool(bool),
+    Integer(i8, i16, i32, i64, u8, u16, u32),
+    Float(f32, f64),
+    String(&str, String),
+}
+
+impl<T> From<Vec<T>> for ConfigValue
+where
+    T: Into<ConfigValue>,
+{
+    fn from(list: Vec<T>) -> Self {
+        ConfigValue::List(list.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T> From<Option<T>> for ConfigValue
+where
+    T: Into<ConfigValue>,
+{
+    fn from(option: Option<T>) -> Self {
+        option.map_or(ConfigValue::Null, Into::into)
+    }
+}
+
+// endregion: CONVERSIONS
+
+// region: SERIALIZER
+
+impl Serialize for ConfigValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            ConfigValue::Null => serializer.serialize_none(),
+            ConfigValue::Bool(value) => serializer.serialize_bool(*value),
+            ConfigValue::Integer(value) => serializer.serialize_i64(*value),
+            ConfigValue::Float(value) => serializer.serialize_f64(*value),
+            ConfigValue::String(string) => serializer.serialize_str(string),
+            ConfigValue::List(list) => serializer.collect_seq(list),
+            ConfigValue::Table(table) => serializer.collect_map(table),
+        }
+    }
+}
+
+/// Serializes any value to a [`ConfigValue`].
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = ConfigValue;
+    type Error = ValueError;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeVariant<SerializeList>;
+    type SerializeMap = SerializeTable;
+    type SerializeStruct = SerializeTable;
+    type SerializeStructVariant = SerializeVariant<SerializeTable>;
+
+    fn serialize_bool(self, value: bool) -> Result<ConfigValue, ValueError> {
+        Ok(ConfigValue::Bool(value))
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<ConfigValue, ValueError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<ConfigValue, ValueError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<ConfigValue, ValueError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<ConfigValue, ValueError> {
+        Ok(ConfigValue::Integer(value))
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<ConfigValue, ValueError> {
+        i64::try_from(value).map(ConfigValue::Integer).map_err(|_| {
+            <ValueError as ser::Error>::custom(format!("the integer {value} is too large"))
+        })
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<ConfigValue, ValueError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<ConfigValue, ValueError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<ConfigValue, ValueError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<ConfigValue, ValueError> {
+        self.serialize_i128(value.into())
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<ConfigValue, ValueError> {
+        i64::try_from(value).map(ConfigValue::Integer).map_err(|_| {
+            <ValueError as ser::Error>::custom(format!("the integer {value} is too large"))
+        })
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<ConfigValue, ValueError> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<ConfigValue, ValueError> {
+        Ok(ConfigValue::Float(value))
+    }
+
+    fn serialize_char(self, value: char) -> Result<ConfigValue, ValueError> {
+        Ok(ConfigValue::String(value.to_string()))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<ConfigValue, ValueError> {
+        Ok(ConfigValue::String(value.to_owned()))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<ConfigValue, ValueError> {
+        Ok(ConfigValue::List(
+            value.iter().copied().map(ConfigValue::from).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<ConfigValue, ValueError> {
+        Ok(ConfigValue::Null)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<ConfigValue, ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<ConfigValue, ValueError> {
+        Ok(ConfigValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<ConfigValue, ValueError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<ConfigValue, ValueError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<ConfigValue, ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<ConfigValue, ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let mut table = BTreeMap::new();
+        table.insert(variant.to_owned(), value.serialize(self)?);
+        Ok(ConfigValue::Table(table))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, ValueError> {
+        Ok(SerializeList {
+            list: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, ValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, ValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeList>, ValueError> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeTable, ValueError> {
+        Ok(SerializeTable {
+            table: BTreeMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeTable, ValueError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeTable>, ValueError> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+struct SerializeList {
+    list: Vec<ConfigValue>,
+}
+
+impl SerializeSeq for SerializeList {
+    type Ok = ConfigValue;
+    type Error = ValueError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.list.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<ConfigValue, ValueError> {
+        Ok(ConfigValue::List(self.list))
+    }
+}
+
+impl SerializeTuple for SerializeList {
+    type Ok = ConfigValue;
+    type Error = ValueError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<ConfigValue, ValueError> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeTupleStruct for SerializeList {
+    type Ok = ConfigValue;
+    type Error = ValueError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<ConfigValue, ValueError> {
+        SerializeSeq::end(self)
+    }
+}
+
+struct SerializeTable {
+    table: BTreeMap<String, ConfigValue>,
+    key: Option<String>,
+}
+
+impl SerializeMap for SerializeTable {
+    type Ok = ConfigValue;
+    type Error = ValueError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        // Keys of other types, like integers, are converted to strings, as in
+        // most config formats
+        self.key = Some(match key.serialize(ValueSerializer)? {
+            ConfigValue::String(string) => string,
+            ConfigValue::Bool(value) => value.to_string(),
+            ConfigValue::Integer(value) => value.to_string(),
+            other => {
+                return Err(<ValueError as ser::Error>::custom(format!(
+                    "the key {other} is not a string"
+                )))
+            }
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_key is called before serialize_value");
+        self.table.insert(key, value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<ConfigValue, ValueError> {
+        Ok(ConfigValue::Table(self.table))
+    }
+}
+
+impl SerializeStruct for SerializeTable {
+    type Ok = ConfigValue;
+    type Error = ValueError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.table
+            .insert(key.to_owned(), value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<ConfigValue, ValueError> {
+        SerializeMap::end(self)
+    }
+}
+
+/// Enum variants with fields are serialized as tables with a single entry,
+/// whose key is the name of the variant.
+struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> SerializeVariant<S> {
+    fn wrap(variant: &'static str, value: ConfigValue) -> ConfigValue {
+        let mut table = BTreeMap::new();
+        table.insert(variant.to_owned(), value);
+        ConfigValue::Table(table)
+    }
+}
+
+impl SerializeTupleVariant for SerializeVariant<SerializeList> {
+    type Ok = ConfigValue;
+    type Error = ValueError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<ConfigValue, ValueError> {
+        Ok(Self::wrap(self.variant, SerializeSeq::end(self.inner)?))
+    }
+}
+
+impl SerializeStructVariant for SerializeVariant<SerializeTable> {
+    type Ok = ConfigValue;
+    type Error = ValueError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), ValueError>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<ConfigValue, ValueError> {
+        Ok(Self::wrap(self.variant, SerializeMap::end(self.inner)?))
+    }
+}
+
+// endregion: SERIALIZER
+
+// region: DESERIALIZER
+
+impl<'de> Deserialize<'de> for ConfigValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = ConfigValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any config value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<ConfigValue, E> {
+        Ok(ConfigValue::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<ConfigValue, E> {
+        Ok(ConfigValue::Integer(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<ConfigValue, E>
+    where
+        E: de::Error,
+    {
+        i64::try_from(value)
+            .map(ConfigValue::Integer)
+            .map_err(|_| E::custom(format!("the integer {value} is too large")))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<ConfigValue, E> {
+        Ok(ConfigValue::Float(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<ConfigValue, E> {
+        Ok(ConfigValue::String(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<ConfigValue, E> {
+        Ok(ConfigValue::String(value))
+    }
+
+    fn visit_none<E>(self) -> Result<ConfigValue, E> {
+        Ok(ConfigValue::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<ConfigValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        ConfigValue::deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<ConfigValue, E> {
+        Ok(ConfigValue::Null)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<ConfigValue, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut list = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(item) = seq.next_element()? {
+            list.push(item);
+        }
+        Ok(ConfigValue::List(list))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<ConfigValue, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut table = BTreeMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            table.insert(key, value);
+        }
+        Ok(ConfigValue::Table(table))
+    }
+}
+
+/// Strings are parsed when a number is requested, like the values of an
+/// [`EnvSource`](super::EnvSource).
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident),* $(,)?) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, ValueError>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    ConfigValue::String(string) => match string.trim().parse() {
+                        Ok(value) => visitor.$visit(value),
+                        Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&string), &visitor)),
+                    },
+                    other => other.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ConfigValue {
+    type Error = ValueError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            ConfigValue::Null => visitor.visit_unit(),
+            ConfigValue::Bool(value) => visitor.visit_bool(value),
+            ConfigValue::Integer(value) => visitor.visit_i64(value),
+            ConfigValue::Float(value) => visitor.visit_f64(value),
+            ConfigValue::String(string) => visitor.visit_string(string),
+            ConfigValue::List(list) => visitor.visit_seq(SeqDeserializer::new(list.into_iter())),
+            ConfigValue::Table(table) => visitor.visit_map(MapDeserializer::new(table.into_iter())),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            ConfigValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            ConfigValue::String(string) => visitor.visit_enum(string.into_deserializer()),
+            ConfigValue::Table(table) if table.len() == 1 => visitor.visit_enum(
+                MapAccessDeserializer::new(MapDeserializer::new(table.into_iter())),
+            ),
+            other => Err(<ValueError as de::Error>::invalid_type(
+                other.unexpected(),
+                &visitor,
+            )),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            ConfigValue::String(string) => match parse_bool(&string) {
+                Some(value) => visitor.visit_bool(value),
+                None => Err(de::Error::invalid_value(Unexpected::Str(&string), &visitor)),
+            },
+            ConfigValue::Integer(value @ (0 | 1)) => visitor.visit_bool(value == 1),
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            ConfigValue::Bool(value) => visitor.visit_string(value.to_string()),
+            ConfigValue::Integer(value) => visitor.visit_string(value.to_string()),
+            ConfigValue::Float(value) => visitor.visit_string(value.to_string()),
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, ValueError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            // Comma-separated lists, as in environment variables
+            ConfigValue::String(string) => {
+                let items = if string.trim().is_empty() {
+                    Vec::new()
+                } else {
+                    string
+                        .split(',')
+                        .map(|item| ConfigValue::String(item.trim().to_owned()))
+                        .collect()
+                };
+                visitor.visit_seq(SeqDeserializer::new(items.into_iter()))
+            }
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf unit unit_struct tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ValueError> for ConfigValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+// endregion: DESERIALIZER
+
+// region: ERRORS
+
+/// The error returned when converting between configuration structs and
+/// [`ConfigValue`]s, or when setting a value at a dotted key.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum ValueError {
+    #[non_exhaustive]
+    #[snafu(display("{message}"), visibility(pub(crate)))]
+    Convert { message: String },
+
+    #[non_exhaustive]
+    #[snafu(display("the field {:?} is missing", key), visibility(pub(crate)))]
+    MissingField { key: String },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("the key {:?} is not a valid dotted key", key),
+        visibility(pub(crate))
+    )]
+    InvalidKey { key: String },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("the key {:?} does not refer to a table or a list item", key),
+        visibility(pub(crate))
+    )]
+    NotATable { key: String },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("the key {:?} does not refer to a field", key),
+        visibility(pub(crate))
+    )]
+    UnknownKey { key: String },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("the value {value} cannot be set at the key {:?}: {source}", key),
+        visibility(pub(crate))
+    )]
+    SetValue {
+        key: String,
+        value: ConfigValue,
+        #[snafu(source(from(ValueError, Box::new)))]
+        source: Box<ValueError>,
+    },
+}
+
+impl ser::Error for ValueError {
+    fn custom<T>(message: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        ValueError::Convert {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl de::Error for ValueError {
+    fn custom<T>(message: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        ValueError::Convert {
+            message: message.to_string(),
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        ValueError::MissingField {
+            key: field.to_owned(),
+        }
+    }
+}
+
+// endregion: ERRORS
+
+// region: IMPORTS
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::{
+    de::{
+        self,
+        value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+        DeserializeOwned, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+        Visitor,
+    },
+    forward_to_deserialize_any,
+    ser::{
+        self, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+        SerializeTupleStruct, SerializeTupleVariant, Serializer,
+    },
+    Deserialize, Serialize,
+};
+
+use snafu::{ensure, OptionExt, Snafu};
+
+use super::text::parse_bool;
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct TestConfig {
+        my_bool: Option<bool>,
+        my_list: Option<Vec<u16>>,
+        my_table: Option<TestTable>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct TestTable {
+        my_port: Option<u16>,
+        my_mode: Option<TestMode>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum TestMode {
+        Fast,
+        Limited(u8),
+    }
+
+    #[test]
+    fn round_trip() {
+        let test_config = TestConfig {
+            my_bool: Some(true),
+            my_list: Some(vec![1, 2]),
+            my_table: Some(TestTable {
+                my_port: None,
+                my_mode: Some(TestMode::Limited(3)),
+            }),
+        };
+
+        let value = ConfigValue::from_config(&test_config).unwrap();
+        assert_eq!(value.get("my_list.1"), Some(&ConfigValue::Integer(2)));
+        assert_eq!(value.get("my_table.my_port"), Some(&ConfigValue::Null));
+        assert_eq!(value.get("my_table.nothing"), None);
+        let keys: Vec<String> = value.entries().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(
+            keys,
+            [
+                "my_bool",
+                "my_list",
+                "my_table.my_mode.Limited",
+                "my_table.my_port"
+            ]
+        );
+        assert_eq!(value.into_config::<TestConfig>().unwrap(), test_config);
+    }
+
+    #[test]
+    fn set_values() {
+        let mut value = ConfigValue::table();
+        value.set("my_table.my_port", 8080.into()).unwrap();
+        value.set("my_table.my_mode", "Fast".into()).unwrap();
+        value.set("my_list", vec![1, 2].into()).unwrap();
+        value.set("my_list.0", 3.into()).unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{ my_list = [3, 2], my_table = { my_mode = "Fast", my_port = 8080 } }"#
+        );
+        let test_config: TestConfig = value.clone().into_config().unwrap();
+        assert_eq!(test_config.my_table.unwrap().my_mode, Some(TestMode::Fast));
+
+        assert!(matches!(
+            value.set("my_list.my_port", 1.into()),
+            Err(ValueError::NotATable { .. })
+        ));
+        assert!(matches!(
+            value.set("my_table..my_port", 1.into()),
+            Err(ValueError::InvalidKey { .. })
+        ));
+    }
+
+    // region: IMPORTS
+
+    use super::*;
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
@@ -0,0 +1,243 @@
+/// Convert an XML document to a text tree. The name of the root element is
+/// ignored, and its contents map to the fields of the configuration struct:
+/// - Attributes and child elements are treated alike, so that
+///   `<server port="80"/>` and `<server><port>80</port></server>` both set
+///   the field `port` of the struct in the field `server`.
+/// - An element with only text content maps to a value, and so does an
+///   element with empty content, like `<name></name>`.
+/// - An empty element, like `<server/>`, maps to an empty struct.
+/// - An element with attributes or child elements maps to a nested struct,
+///   and any text content it also has is available under the key `$text`.
+/// - Repeated child elements (or attributes and child elements of the same
+///   name) map to a list.
+///
+/// Namespace prefixes are ignored, and comments, processing instructions and
+/// the declaration are skipped.
+pub(crate) fn text_from_str(string: &str) -> Result<Text, TextError> {
+    let mut reader = Reader::from_str(string);
+    reader.config_mut().trim_text(true);
+
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root: Option<Text> = None;
+    loop {
+        let event = reader.read_event().map_err(Box::from).context(ParseSnafu)?;
+        match event {
+            Event::Start(start) => stack.push(Element::new(&start, false)?),
+            Event::Empty(start) => {
+                let element = Element::new(&start, true)?;
+                close(element, &mut stack, &mut root)?;
+            }
+            Event::End(_) => {
+                // The reader checks that end tags match start tags
+                if let Some(element) = stack.pop() {
+                    close(element, &mut stack, &mut root)?;
+                }
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    let text = text.unescape().map_err(Box::from).context(ParseSnafu)?;
+                    element.text.push_str(&text);
+                }
+            }
+            Event::CData(cdata) => {
+                if let Some(element) = stack.last_mut() {
+                    let cdata = cdata.decode().map_err(Box::from).context(ParseSnafu)?;
+                    element.text.push_str(&cdata);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    match (root, stack.is_empty()) {
+        (Some(root), true) => Ok(root),
+        (None, true) => Err(TextError::custom("the XML document has no root element")),
+        (_, false) => Err(TextError::custom("the XML document ends unexpectedly")),
+    }
+}
+
+/// An element whose end tag has not been read yet.
+struct Element {
+    name: String,
+    entries: Vec<(String, Text)>,
+    text: String,
+    is_empty: bool,
+}
+
+impl Element {
+    fn new(start: &BytesStart<'_>, is_empty: bool) -> Result<Self, TextError> {
+        let mut entries = Vec::new();
+        for attribute in start.attributes() {
+            let attribute = attribute.map_err(Box::from).context(ParseSnafu)?;
+            // Namespace declarations are not part of the configuration
+            if attribute.key.as_namespace_binding().is_some() {
+                continue;
+            }
+            let value = attribute
+                .unescape_value()
+                .map_err(Box::from)
+                .context(ParseSnafu)?;
+            entries.push((
+                local_name(attribute.key.local_name().as_ref()),
+                Text::Value(value.into_owned()),
+            ));
+        }
+        Ok(Self {
+            name: local_name(start.local_name().as_ref()),
+            entries,
+            text: String::new(),
+            is_empty,
+        })
+    }
+
+    fn into_text(self) -> (String, Text) {
+        let text = if self.entries.is_empty() && !self.is_empty {
+            Text::Value(self.text)
+        } else {
+            let mut entries = self.entries;
+            if !self.text.is_empty() {
+                entries.push((String::from("$text"), Text::Value(self.text)));
+            }
+            Text::table_from_entries(entries)
+        };
+        (self.name, text)
+    }
+}
+
+/// Add a closed element to its parent, or make it the root.
+fn close(
+    element: Element,
+    stack: &mut [Element],
+    root: &mut Option<Text>,
+) -> Result<(), TextError> {
+    let (name, text) = element.into_text();
+    match stack.last_mut() {
+        Some(parent) => parent.entries.push((name, text)),
+        None if root.is_some() => {
+            return Err(TextError::custom(
+                "the XML document has more than one root element",
+            ))
+        }
+        None => {
+            *root = Some(match text {
+                // The root element always maps to the configuration struct
+                Text::Value(_) => Text::table(),
+                text => text,
+            })
+        }
+    }
+    Ok(())
+}
+
+/// Write a table as an XML document with the root element `config`, the
+/// counterpart of [`text_from_str()`]:
+/// - Every entry of the table is written as a child element, with its value
+///   as the text content.
+/// - Tables are written as elements with child elements, and the value under
+///   the key `$text` as their text content. Empty tables are written as
+///   empty elements, like `<server/>`.
+/// - Lists are written as repeated elements, and empty lists as elements
+///   with empty content, which are read back as lists without items.
+///
+/// Null values are left out, so that their fields stay unset.
+pub(crate) fn value_to_string(value: &ConfigValue) -> Result<String, ValueError> {
+    if !matches!(value, ConfigValue::Table(_)) {
+        return Err(ValueError::custom("only tables can be written as XML"));
+    }
+    let mut string = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    write_element(&mut string, "config", value, 0)?;
+    Ok(string)
+}
+
+fn write_element(
+    string: &mut String,
+    name: &str,
+    value: &ConfigValue,
+    depth: usize,
+) -> Result<(), ValueError> {
+    if !is_name(name) {
+        return Err(ValueError::custom(format!(
+            "the key {:?} is not a valid XML element name",
+            name
+        )));
+    }
+    let indent = "    ".repeat(depth);
+    match value {
+        ConfigValue::Null => {}
+        ConfigValue::List(items) if items.is_empty() => {
+            string.push_str(&format!("{indent}<{name}></{name}>\n"))
+        }
+        ConfigValue::List(items) => {
+            for item in items {
+                if matches!(item, ConfigValue::List(_)) {
+                    return Err(ValueError::custom(
+                        "lists of lists cannot be written as XML",
+                    ));
+                }
+                write_element(string, name, item, depth)?;
+            }
+        }
+        ConfigValue::Table(table) if table.values().all(ConfigValue::is_null) => {
+            string.push_str(&format!("{indent}<{name}/>\n"))
+        }
+        ConfigValue::Table(table) => {
+            string.push_str(&format!("{indent}<{name}>"));
+            if let Some(text) = table.get("$text").filter(|text| !text.is_null()) {
+                string.push_str(&escape(scalar_to_string(text)));
+            }
+            string.push('\n');
+            for (key, value) in table.iter().filter(|(key, _)| *key != "$text") {
+                write_element(string, key, value, depth + 1)?;
+            }
+            string.push_str(&format!("{indent}</{name}>\n"));
+        }
+        value => string.push_str(&format!(
+            "{indent}<{name}>{}</{name}>\n",
+            escape(scalar_to_string(value))
+        )),
+    }
+    Ok(())
+}
+
+fn scalar_to_string(value: &ConfigValue) -> String {
+    match value {
+        ConfigValue::Bool(value) => value.to_string(),
+        ConfigValue::Integer(integer) => integer.to_string(),
+        ConfigValue::Float(float) => format!("{:?}", float),
+        ConfigValue::String(string) => string.clone(),
+        _ => String::new(),
+    }
+}
+
+/// Whether the key is a valid element name, without a namespace prefix.
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn local_name(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).into_owned()
+}
+
+// region: IMPORTS
+
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use serde::de::Error as _;
+
+use snafu::ResultExt;
+
+use super::{
+    text::{ParseSnafu, Text, TextError},
+    ConfigValue, ValueError,
+};
+
+// endregion: IMPORTS
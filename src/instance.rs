@@ -0,0 +1,501 @@
+/// How an application was launched: its arguments (including the name of
+/// the executable, like `std::env::args_os()`) and its working directory,
+/// against which relative paths in the arguments are to be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    args: Vec<OsString>,
+    cwd: PathBuf,
+}
+
+impl Invocation {
+    /// Invocations longer than this in total, counting the length prefixes
+    /// of the fields, are refused, so that a faulty client cannot make the
+    /// primary instance allocate without bounds.
+    const MAX_LENGTH: usize = 1 << 20;
+
+    pub fn new(args: impl IntoIterator<Item = impl Into<OsString>>, cwd: impl AsRef<Path>) -> Self {
+        Self {
+            args: args.into_iter().map(Into::into).collect(),
+            cwd: cwd.as_ref().to_owned(),
+        }
+    }
+
+    /// The invocation of the current process.
+    pub fn current() -> Result<Self, Error> {
+        let cwd = env::current_dir().context(CurrentDirectorySnafu)?;
+        Ok(Self::new(env::args_os(), cwd))
+    }
+
+    pub fn args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// Write the working directory and the arguments, each prefixed with its
+    /// length, after the number of fields.
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let fields =
+            iter::once(self.cwd.as_os_str()).chain(self.args.iter().map(OsString::as_os_str));
+        writer.write_all(&(1 + self.args.len() as u32).to_be_bytes())?;
+        for field in fields {
+            writer.write_all(&(field.len() as u32).to_be_bytes())?;
+            writer.write_all(field.as_bytes())?;
+        }
+        writer.flush()
+    }
+
+    fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let read_length = |reader: &mut dyn Read| {
+            let mut length = [0; 4];
+            reader.read_exact(&mut length)?;
+            Ok::<_, io::Error>(u32::from_be_bytes(length) as usize)
+        };
+        let too_long = || io::Error::new(io::ErrorKind::InvalidData, "the invocation is too long");
+        let count = read_length(reader)?;
+        // Every field is prefixed with its length
+        let mut remaining = Self::MAX_LENGTH
+            .checked_sub(count.saturating_mul(4))
+            .ok_or_else(too_long)?;
+        let mut fields = Vec::new();
+        for _ in 0..count {
+            let length = read_length(reader)?;
+            remaining = remaining.checked_sub(length).ok_or_else(too_long)?;
+            let mut field = vec![0; length];
+            reader.read_exact(&mut field)?;
+            fields.push(OsString::from_vec(field));
+        }
+        let mut fields = fields.into_iter();
+        let cwd = fields
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the invocation is empty"))?;
+        Ok(Self {
+            cwd: PathBuf::from(cwd),
+            args: fields.collect(),
+        })
+    }
+}
+
+/// Whether the current process is the only running instance of the
+/// application for the user, enforced with an exclusive [`FileLock`] on the
+/// lock file `instance` in the runtime directory (see
+/// [`AppDirs::lock_path()`]):
+/// - The first instance to launch takes the lock and becomes the
+///   [`Primary`] instance. It listens on the Unix domain socket
+///   `instance.sock` in the runtime directory, which only the user can
+///   access.
+/// - Instances launched while the lock is held are [`Secondary`]: they
+///   forward their [`Invocation`] to the primary instance through the
+///   socket, and should then exit.
+///
+/// [`Primary`]: Instance::Primary
+/// [`Secondary`]: Instance::Secondary
+///
+/// ```no_run
+/// # use dapp::{instance::Instance, path::AppDirs};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let app_dirs = AppDirs::new("myapp")?;
+/// match Instance::acquire(&app_dirs)? {
+///     Instance::Primary(primary) => {
+///         let invocations = primary.receiver();
+///         // Open the files of every later invocation in the same window
+///     }
+///     Instance::Secondary => return Ok(()),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum Instance {
+    Primary(Primary),
+    Secondary,
+}
+
+impl Instance {
+    /// How long a secondary instance waits for the primary instance, which
+    /// may just be starting, to accept its invocation.
+    pub const FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Become the primary instance, or forward the invocation of the current
+    /// process to it.
+    pub fn acquire(app_dirs: &AppDirs) -> Result<Self, Error> {
+        Self::acquire_with(app_dirs, &Invocation::current()?)
+    }
+
+    /// Like [`acquire()`](Instance::acquire), but forward the given
+    /// invocation.
+    pub fn acquire_with(app_dirs: &AppDirs, invocation: &Invocation) -> Result<Self, Error> {
+        let lock_path = app_dirs.lock_path("instance").context(PathSnafu)?;
+        let socket_path = app_dirs
+            .place_runtime_file("instance.sock")
+            .context(PathSnafu)?;
+        match FileLock::try_lock(&lock_path, LockMode::Exclusive).context(PathSnafu)? {
+            Some(lock) => Primary::listen(lock, socket_path).map(Instance::Primary),
+            None => {
+                forward(&socket_path, invocation)?;
+                Ok(Instance::Secondary)
+            }
+        }
+    }
+}
+
+/// The primary instance, which receives the invocations of secondary
+/// instances. It remains the primary instance until it is dropped, or, after
+/// [`receiver()`](Primary::receiver) or
+/// [`on_invocation()`](Primary::on_invocation), until the process exits.
+#[derive(Debug)]
+pub struct Primary {
+    socket_path: PathBuf,
+    listener: UnixListener,
+    _lock: FileLock,
+}
+
+impl Primary {
+    fn listen(lock: FileLock, socket_path: PathBuf) -> Result<Self, Error> {
+        // The socket is bound under a temporary name and made private to the
+        // user before it is renamed into place, which also replaces a socket
+        // left behind by a primary instance which crashed
+        let mut temporary_path = socket_path.clone().into_os_string();
+        temporary_path.push(format!(".{}.tmp", process::id()));
+        let temporary_path = PathBuf::from(temporary_path);
+        let _ = fs::remove_file(&temporary_path);
+        let listener = UnixListener::bind(&temporary_path)
+            .and_then(|listener| {
+                fs::set_permissions(&temporary_path, Permissions::from_mode(0o600))?;
+                fs::rename(&temporary_path, &socket_path)?;
+                Ok(listener)
+            })
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&temporary_path);
+            })
+            .context(ListenSnafu { path: &socket_path })?;
+        Ok(Self {
+            socket_path,
+            listener,
+            _lock: lock,
+        })
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Wait for the next secondary instance, and receive its invocation.
+    /// Instances of other users are refused, even if they can access the
+    /// socket.
+    pub fn accept(&self) -> Result<Invocation, Error> {
+        let (mut stream, _) = self.listener.accept().context(AcceptSnafu)?;
+        let uid = peer_uid(&stream).context(ReceiveSnafu)?;
+        // SAFETY: `geteuid()` cannot fail and has no preconditions
+        ensure!(uid == unsafe { libc::geteuid() }, ForeignUserSnafu { uid });
+        stream
+            .set_read_timeout(Some(Instance::FORWARD_TIMEOUT))
+            .context(ReceiveSnafu)?;
+        let invocation = Invocation::read_from(&mut stream).context(ReceiveSnafu)?;
+        // Acknowledge the invocation, so that the secondary instance can exit
+        stream.write_all(&[0]).context(ReceiveSnafu)?;
+        Ok(invocation)
+    }
+
+    /// Receive the invocations of secondary instances in a background
+    /// thread, through a channel. Invocations which cannot be received are
+    /// logged and skipped, see [`on_invocation()`](Primary::on_invocation).
+    pub fn receiver(self) -> Receiver<Invocation> {
+        let (sender, receiver) = mpsc::channel();
+        self.on_invocation(move |invocation| {
+            // Fails only if the receiver was dropped, when nobody waits for them
+            let _ = sender.send(invocation);
+        });
+        receiver
+    }
+
+    /// Call the closure with the invocation of every secondary instance, in
+    /// a background thread. Invocations which cannot be received, or which
+    /// are refused, are logged and skipped. If connections cannot be
+    /// accepted at all, the thread waits longer after every failure in a
+    /// row, so that a persistent error does not keep it busy, and it stops if
+    /// the socket itself cannot be used anymore.
+    pub fn on_invocation<F>(self, mut f: F) -> JoinHandle<()>
+    where
+        F: FnMut(Invocation) + Send + 'static,
+    {
+        const MIN_BACKOFF: Duration = Duration::from_millis(10);
+        const MAX_BACKOFF: Duration = Duration::from_secs(1);
+        thread::spawn(move || {
+            let mut backoff = Duration::ZERO;
+            loop {
+                match self.accept() {
+                    Ok(invocation) => {
+                        backoff = Duration::ZERO;
+                        f(invocation);
+                    }
+                    Err(error) if is_fatal(&error) => {
+                        error!("stopped receiving invocations: {error}");
+                        return;
+                    }
+                    Err(error @ Error::Accept { .. }) => {
+                        warn!("{error}");
+                        backoff = (backoff * 2).clamp(MIN_BACKOFF, MAX_BACKOFF);
+                        thread::sleep(backoff);
+                    }
+                    // A single connection failed or was refused, which the
+                    // next one does not have to wait for, since any local
+                    // user could stall the thread otherwise
+                    Err(error) => {
+                        backoff = Duration::ZERO;
+                        warn!("{error}");
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Whether the error means that the listening socket cannot be used anymore,
+/// rather than that a single invocation failed.
+fn is_fatal(error: &Error) -> bool {
+    match error {
+        Error::Accept { source } => {
+            matches!(source.raw_os_error(), Some(libc::EBADF | libc::EINVAL))
+        }
+        _ => false,
+    }
+}
+
+/// The user of the process at the other end of the stream.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut length = mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: the buffer is a `ucred` of the given length, and the file
+    // descriptor is valid as long as the stream is open
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut credentials as *mut libc::ucred).cast(),
+            &mut length,
+        )
+    };
+    match result {
+        0 => Ok(credentials.uid),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// The user of the process at the other end of the stream.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: the file descriptor is valid as long as the stream is open
+    match unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } {
+        0 => Ok(uid),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+impl Drop for Primary {
+    fn drop(&mut self) {
+        // Before the lock is released, so that the next primary instance
+        // never has its socket deleted
+        let _ = fs::remove_file(&self.socket_path);
+    }
+}
+
+/// Send the invocation to the primary instance, waiting for it to listen if
+/// it is just starting.
+fn forward(socket_path: &Path, invocation: &Invocation) -> Result<(), Error> {
+    let deadline = Instant::now() + Instance::FORWARD_TIMEOUT;
+    let mut stream = loop {
+        match UnixStream::connect(socket_path) {
+            Ok(stream) => break stream,
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+                ) && Instant::now() < deadline =>
+            {
+                thread::sleep(Duration::from_millis(10))
+            }
+            Err(error) => return Err(error).context(ForwardSnafu { path: socket_path }),
+        }
+    };
+    stream
+        .set_read_timeout(Some(Instance::FORWARD_TIMEOUT))
+        .and_then(|()| invocation.write_to(&mut stream))
+        .and_then(|()| stream.read_exact(&mut [0]))
+        .context(ForwardSnafu { path: socket_path })
+}
+
+// region: ERRORS
+
+/// The error returned when enforcing a single instance.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[non_exhaustive]
+    #[snafu(display("{source}"), visibility(pub(crate)))]
+    Path { source: crate::path::Error },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("could not find the current directory: {source}"),
+        visibility(pub(crate))
+    )]
+    CurrentDirectory { source: io::Error },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("could not listen on the socket {:?}: {source}", path),
+        visibility(pub(crate))
+    )]
+    Listen { path: PathBuf, source: io::Error },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("could not accept connections on the socket: {source}"),
+        visibility(pub(crate))
+    )]
+    Accept { source: io::Error },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("could not receive an invocation: {source}"),
+        visibility(pub(crate))
+    )]
+    Receive { source: io::Error },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("refused an invocation from the user with UID {uid}"),
+        visibility(pub(crate))
+    )]
+    ForeignUser { uid: u32 },
+
+    #[non_exhaustive]
+    #[snafu(
+        display(
+            "could not forward the invocation to the primary instance at {:?}: {source}",
+            path
+        ),
+        visibility(pub(crate))
+    )]
+    Forward { path: PathBuf, source: io::Error },
+}
+
+// endregion: ERRORS
+
+// region: IMPORTS
+
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, Permissions},
+    io::{self, Read, Write},
+    iter,
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::PermissionsExt,
+        io::AsRawFd,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process,
+    sync::mpsc::{self, Receiver},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::mem;
+
+use snafu::{ensure, ResultExt, Snafu};
+
+use tracing::{error, warn};
+
+use crate::path::{AppDirs, FileLock, LockMode};
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn single_instance() {
+        let _xdg_env = crate::XDG_ENV_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let xdg_runtime_dir = env::var_os("XDG_RUNTIME_DIR");
+        let runtime_dir = tempfile::tempdir().unwrap();
+        fs::set_permissions(runtime_dir.path(), fs::Permissions::from_mode(0o700)).unwrap();
+        env::set_var("XDG_RUNTIME_DIR", runtime_dir.path());
+        let app_dirs = AppDirs::new("dapp-test-instance").unwrap();
+        match xdg_runtime_dir {
+            Some(xdg_runtime_dir) => env::set_var("XDG_RUNTIME_DIR", xdg_runtime_dir),
+            None => env::remove_var("XDG_RUNTIME_DIR"),
+        }
+
+        let first = Invocation::new(["myapp"], "/");
+        let second = Invocation::new(["myapp", "notes.txt", "--new-window"], "/home");
+        let Instance::Primary(primary) = Instance::acquire_with(&app_dirs, &first).unwrap() else {
+            panic!("the first instance is not the primary one");
+        };
+        assert_eq!(
+            fs::metadata(primary.socket_path())
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777,
+            0o600
+        );
+        let invocations = primary.receiver();
+        assert!(matches!(
+            Instance::acquire_with(&app_dirs, &second).unwrap(),
+            Instance::Secondary
+        ));
+        assert_eq!(
+            invocations.recv_timeout(Duration::from_secs(5)).unwrap(),
+            second
+        );
+    }
+
+    #[test]
+    fn invocation_length() {
+        let invocation = Invocation::new(["myapp", "notes.txt"], "/home");
+        let mut bytes = Vec::new();
+        invocation.write_to(&mut bytes).unwrap();
+        assert_eq!(
+            Invocation::read_from(&mut bytes.as_slice()).unwrap(),
+            invocation
+        );
+
+        // Too many fields, or fields too long in total
+        let too_many = u32::MAX.to_be_bytes();
+        assert!(Invocation::read_from(&mut too_many.as_slice()).is_err());
+        let mut too_long = 3u32.to_be_bytes().to_vec();
+        for _ in 0..3 {
+            too_long.extend((Invocation::MAX_LENGTH as u32 / 3).to_be_bytes());
+            too_long.extend(vec![b'a'; Invocation::MAX_LENGTH / 3]);
+        }
+        let error = Invocation::read_from(&mut too_long.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // region: IMPORTS
+
+    use std::sync::PoisonError;
+
+    use super::*;
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
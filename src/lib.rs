@@ -3,6 +3,9 @@
 #[cfg(feature = "config")]
 pub mod config;
 
+#[cfg(all(feature = "instance", unix))]
+pub mod instance;
+
 #[cfg(feature = "log")]
 pub mod log;
 
@@ -10,3 +13,38 @@ pub mod log;
 pub mod path;
 
 // endregion: MODULES
+
+// region: TESTS
+
+/// Held by tests which set the XDG environment variables (or any other
+/// environment variables), since tests run concurrently in the same process.
+#[cfg(all(test, feature = "path"))]
+pub(crate) static XDG_ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// An environment variable set by a test while holding [`XDG_ENV_LOCK`]. It
+/// is removed when it is dropped, even if the test fails.
+#[cfg(all(test, feature = "path"))]
+pub(crate) struct TestEnvVar {
+    name: &'static str,
+    _lock: std::sync::MutexGuard<'static, ()>,
+}
+
+#[cfg(all(test, feature = "path"))]
+impl TestEnvVar {
+    pub(crate) fn set(name: &'static str, value: &str) -> Self {
+        let lock = XDG_ENV_LOCK
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        std::env::set_var(name, value);
+        Self { name, _lock: lock }
+    }
+}
+
+#[cfg(all(test, feature = "path"))]
+impl Drop for TestEnvVar {
+    fn drop(&mut self) {
+        std::env::remove_var(self.name);
+    }
+}
+
+// endregion: TESTS
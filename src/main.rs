@@ -0,0 +1,370 @@
+//! `dapp`: a command-line tool for troubleshooting applications built on
+//! this crate. It validates, converts and inspects config files in any of the
+//! formats compiled into the crate, and prints the XDG locations an
+//! application would use.
+
+/// Validate, convert and inspect config files, and print the XDG locations
+/// of applications.
+#[derive(Debug, Parser)]
+#[command(name = "dapp", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Validate, convert and inspect config files
+    #[command(subcommand)]
+    Config(ConfigCommand),
+
+    /// Print the XDG base directories of an application, and whether they
+    /// exist and are writable
+    #[cfg(unix)]
+    Paths {
+        /// The name of the application, as used for its directories
+        app_name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Check that a config file can be parsed
+    Validate {
+        #[command(flatten)]
+        file: ConfigFile,
+    },
+
+    /// Print a config file in another format
+    Convert {
+        /// The format to convert to
+        #[arg(long, value_parser = parse_format)]
+        to: Format,
+
+        #[command(flatten)]
+        file: ConfigFile,
+    },
+
+    /// Print the value at a dotted key (for example, `network.timeout` or
+    /// `hosts.0`) of a config file
+    Get {
+        #[command(flatten)]
+        file: ConfigFile,
+
+        /// The dotted key of the value
+        key: String,
+    },
+
+    /// Print every key of a config file with its value
+    List {
+        #[command(flatten)]
+        file: ConfigFile,
+    },
+}
+
+#[derive(Debug, Args)]
+struct ConfigFile {
+    /// The format of the config file, if it cannot be guessed from its
+    /// extension
+    #[arg(long, value_parser = parse_format)]
+    format: Option<Format>,
+
+    /// The config file
+    path: PathBuf,
+}
+
+impl ConfigFile {
+    fn format(&self) -> Result<Format, CliError> {
+        self.format
+            .or_else(|| Format::from_path(&self.path))
+            .context(GuessFormatSnafu { path: &self.path })
+    }
+
+    /// Load the config file without any knowledge of the configuration struct
+    /// it belongs to.
+    fn load(&self) -> Result<(Format, ConfigValue), CliError> {
+        let format = self.format()?;
+        let value = format
+            .value_from_filepath(&self.path)
+            .context(ConfigSnafu)?;
+        Ok((format, value))
+    }
+}
+
+fn parse_format(name: &str) -> Result<Format, String> {
+    Format::from_name(name).ok_or_else(|| {
+        let names: Vec<&str> = Format::ALL.iter().map(|format| format.name()).collect();
+        format!("expected one of: {}", names.join(", "))
+    })
+}
+
+fn main() -> ExitCode {
+    let result = run(Cli::parse(), &mut io::stdout().lock());
+    exit(result, &mut io::stderr().lock())
+}
+
+/// Report the error of a command, if any, and turn its result into the exit
+/// code of the tool.
+fn exit(result: Result<(), CliError>, err: &mut impl Write) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            // Nothing is left to report a failure to write the error to
+            let _ = writeln!(err, "error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run a command, printing its output to `out`.
+fn run(cli: Cli, out: &mut impl Write) -> Result<(), CliError> {
+    match cli.command {
+        Command::Config(ConfigCommand::Validate { file }) => validate(&file, out),
+        Command::Config(ConfigCommand::Convert { to, file }) => convert(&file, to, out),
+        Command::Config(ConfigCommand::Get { file, key }) => get(&file, &key, out),
+        Command::Config(ConfigCommand::List { file }) => list(&file, out),
+        #[cfg(unix)]
+        Command::Paths { app_name } => paths(&app_name, out),
+    }
+}
+
+fn validate(file: &ConfigFile, out: &mut impl Write) -> Result<(), CliError> {
+    let (format, _) = file.load()?;
+    writeln!(out, "{}: valid {}", file.path.display(), format).context(WriteSnafu)
+}
+
+fn convert(file: &ConfigFile, to: Format, out: &mut impl Write) -> Result<(), CliError> {
+    let (_, value) = file.load()?;
+    let mut string = to.config_to_string(&value).context(ConfigSnafu)?;
+    if !string.ends_with('\n') {
+        string.push('\n');
+    }
+    write!(out, "{string}").context(WriteSnafu)
+}
+
+fn get(file: &ConfigFile, key: &str, out: &mut impl Write) -> Result<(), CliError> {
+    let (_, value) = file.load()?;
+    match value.get(key).context(MissingKeySnafu { key })? {
+        // Strings are printed without quotes, for use in scripts
+        ConfigValue::String(string) => writeln!(out, "{string}"),
+        value => writeln!(out, "{value}"),
+    }
+    .context(WriteSnafu)
+}
+
+fn list(file: &ConfigFile, out: &mut impl Write) -> Result<(), CliError> {
+    let (_, value) = file.load()?;
+    for (key, value) in value.entries() {
+        writeln!(out, "{key} = {value}").context(WriteSnafu)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn paths(app_name: &str, out: &mut impl Write) -> Result<(), CliError> {
+    let app_dirs = AppDirs::new(app_name).context(PathSnafu)?;
+    writeln!(out, "{:<8} {}", "strategy", app_dirs.strategy()).context(WriteSnafu)?;
+    for kind in DirKind::ALL {
+        match app_dirs.origin(kind) {
+            Some(origin) if origin != app_dirs.strategy() => {
+                writeln!(out, "{:<8} {} from {}", "strategy", kind, origin).context(WriteSnafu)?
+            }
+            _ => {}
+        }
+    }
+    print_path(out, "config", app_dirs.config_dir())?;
+    print_path(out, "data", app_dirs.data_dir())?;
+    print_path(out, "cache", app_dirs.cache_dir())?;
+    print_path(out, "state", app_dirs.state_dir())?;
+    match app_dirs.runtime_dir() {
+        Some(runtime_dir) => print_path(out, "runtime", runtime_dir)?,
+        None => writeln!(
+            out,
+            "{:<8} unavailable: $XDG_RUNTIME_DIR and the fallback are insecure",
+            "runtime"
+        )
+        .context(WriteSnafu)?,
+    }
+    for directory in app_dirs.config_dirs().skip(1) {
+        print_path(out, "config", directory)?;
+    }
+    for directory in app_dirs.data_dirs().skip(1) {
+        print_path(out, "data", directory)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn print_path(out: &mut impl Write, name: &str, path: &Path) -> Result<(), CliError> {
+    let status = match (path.exists(), path.check(PathRequirement::Creatable)) {
+        (true, Ok(())) => String::from("exists, writable"),
+        (true, Err(issue)) => format!("exists, read-only: {issue}"),
+        (false, Ok(())) => String::from("missing, can be created"),
+        (false, Err(issue)) => format!("missing, cannot be created: {issue}"),
+    };
+    writeln!(out, "{:<8} {} ({})", name, path.display(), status).context(WriteSnafu)
+}
+
+// region: ERRORS
+
+#[derive(Debug, Snafu)]
+enum CliError {
+    #[snafu(display(
+        "could not guess the format of {:?} from its extension, use --format",
+        path
+    ))]
+    GuessFormat { path: PathBuf },
+
+    #[snafu(display("{source}"))]
+    Config { source: dapp::config::Error },
+
+    #[snafu(display("there is no value at the key {:?}", key))]
+    MissingKey { key: String },
+
+    #[cfg(unix)]
+    #[snafu(display("{source}"))]
+    Path { source: dapp::path::Error },
+
+    #[snafu(display("could not write the output: {source}"))]
+    Write { source: io::Error },
+}
+
+// endregion: ERRORS
+
+// region: IMPORTS
+
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+#[cfg(unix)]
+use std::path::Path;
+
+use clap::{Args, Parser, Subcommand};
+
+use snafu::{OptionExt, ResultExt, Snafu};
+
+use dapp::config::{ConfigValue, Format};
+
+#[cfg(unix)]
+use dapp::path::{AppDirs, DirKind, PathRequirement, ValidPath};
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
+    /// Run the tool with `args`, returning its exit code, output and errors.
+    fn dapp(args: &[&str]) -> (ExitCode, String, String) {
+        let cli = Cli::try_parse_from(std::iter::once("dapp").chain(args.iter().copied())).unwrap();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = exit(run(cli, &mut out), &mut err);
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    /// A temporary config file named `name` with `contents`.
+    fn config_file(directory: &TempDir, name: &str, contents: &str) -> String {
+        let path = directory.path().join(name);
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn validate() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = config_file(&directory, "config.json", r#"{"port": 80}"#);
+        let (code, out, _) = dapp(&["config", "validate", &path]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(out, format!("{path}: valid json\n"));
+
+        let path = config_file(&directory, "invalid.json", r#"{"port": "#);
+        let (code, out, err) = dapp(&["config", "validate", &path]);
+        assert_eq!(code, ExitCode::FAILURE);
+        assert_eq!(out, "");
+        assert!(err.starts_with("error: "));
+
+        let path = config_file(&directory, "config.unknown", "");
+        let (code, _, err) = dapp(&["config", "validate", &path]);
+        assert_eq!(code, ExitCode::FAILURE);
+        assert!(err.contains("--format"));
+    }
+
+    #[test]
+    fn convert() {
+        let directory = tempfile::tempdir().unwrap();
+        let json = r#"{"hosts": ["a", "b"], "network": {"port": 80, "secure": true}}"#;
+        let path = config_file(&directory, "config.json", json);
+        let (code, yaml, _) = dapp(&["config", "convert", "--to", "yaml", &path]);
+        assert_eq!(code, ExitCode::SUCCESS);
+
+        // Converting back gives the same config
+        let yaml_path = config_file(&directory, "config.yaml", &yaml);
+        let (code, out, _) = dapp(&["config", "convert", "--to", "json", &yaml_path]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        let converted_path = config_file(&directory, "converted.json", &out);
+        assert_eq!(
+            Format::Json.value_from_filepath(converted_path).unwrap(),
+            Format::Json.value_from_filepath(path).unwrap()
+        );
+    }
+
+    #[test]
+    fn get() {
+        let directory = tempfile::tempdir().unwrap();
+        let json = r#"{"hosts": ["a", "b"], "network": {"port": 80}}"#;
+        let path = config_file(&directory, "config.json", json);
+        assert_eq!(dapp(&["config", "get", &path, "hosts.1"]).1, "b\n");
+        assert_eq!(dapp(&["config", "get", &path, "network.port"]).1, "80\n");
+
+        let (code, out, err) = dapp(&["config", "get", &path, "network.timeout"]);
+        assert_eq!(code, ExitCode::FAILURE);
+        assert_eq!(out, "");
+        assert_eq!(
+            err,
+            "error: there is no value at the key \"network.timeout\"\n"
+        );
+    }
+
+    #[test]
+    fn list() {
+        let directory = tempfile::tempdir().unwrap();
+        let json = r#"{"hosts": ["a"], "network": {"port": 80}}"#;
+        let path = config_file(&directory, "config.json", json);
+        let (code, out, _) = dapp(&["config", "list", &path]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(out, "hosts = [\"a\"]\nnetwork.port = 80\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths() {
+        let (code, out, _) = dapp(&["paths", "dapp-test"]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        let names: Vec<&str> = out
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .collect();
+        for name in ["strategy", "config", "data", "cache", "state", "runtime"] {
+            assert!(names.contains(&name), "{name} is missing from {out:?}");
+        }
+    }
+
+    // region: IMPORTS
+
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
@@ -1,3 +1,31 @@
+// region: MODULES
+
+#[cfg(unix)]
+mod app_dirs;
+#[cfg(unix)]
+mod atomic;
+mod check;
+#[cfg(unix)]
+mod create;
+#[cfg(unix)]
+mod lock;
+
+// endregion: MODULES
+
+// region: RE-EXPORTS
+
+#[cfg(unix)]
+pub use app_dirs::{AppDirs, DirKind, DirOrigin};
+#[cfg(unix)]
+pub use atomic::AtomicWriter;
+pub use check::{PathIssue, PathReport, PathRequirement};
+#[cfg(unix)]
+pub use create::{create_file_private, ensure_dir};
+#[cfg(unix)]
+pub use lock::{FileLock, LockMode};
+
+// endregion: RE-EXPORTS
+
 /// To be implemented by any path-like type to indicate whether it exists,
 /// and to be able to determine what actions can be done with it.
 /// The meanings of the named lifetimes, associate types, and generic types are
@@ -18,6 +46,34 @@ pub trait ValidPath<'a, P> {
 
     fn is_creatable(&self) -> bool;
 
+    /// Whether the path is a regular file, following symbolic links.
+    fn is_file(&self) -> bool;
+
+    /// Whether the path is a directory, following symbolic links.
+    fn is_dir(&self) -> bool;
+
+    /// Whether the path itself is a symbolic link.
+    fn is_symlink(&self) -> bool;
+
+    /// Whether the path is owned by the effective user of the process,
+    /// following symbolic links.
+    #[cfg(unix)]
+    fn is_owned_by_current_user(&self) -> bool;
+
+    /// Whether the path cannot be accessed at all by its group or by other
+    /// users, that is, whether its mode is like 0600 or 0700, following
+    /// symbolic links.
+    #[cfg(unix)]
+    fn is_private(&self) -> bool;
+
+    /// Whether the path is a directory without any entries.
+    fn is_empty_dir(&self) -> bool;
+
+    /// Like the predicates above, but explain why the path does not meet the
+    /// requirement, for example, which directory containing it cannot be
+    /// accessed.
+    fn check(&self, requirement: PathRequirement) -> Result<(), PathIssue>;
+
     fn largest_valid_subset(&'a self) -> Option<Self::P1>;
 }
 
@@ -54,6 +110,38 @@ where
         }
     }
 
+    fn is_file(&self) -> bool {
+        self.as_ref().is_file()
+    }
+
+    fn is_dir(&self) -> bool {
+        self.as_ref().is_dir()
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.as_ref().is_symlink()
+    }
+
+    #[cfg(unix)]
+    fn is_owned_by_current_user(&self) -> bool {
+        // SAFETY: `geteuid()` cannot fail and has no preconditions
+        let current_user = unsafe { libc::geteuid() };
+        fs::metadata(self).is_ok_and(|metadata| metadata.uid() == current_user)
+    }
+
+    #[cfg(unix)]
+    fn is_private(&self) -> bool {
+        fs::metadata(self).is_ok_and(|metadata| metadata.mode() & 0o077 == 0)
+    }
+
+    fn is_empty_dir(&self) -> bool {
+        fs::read_dir(self).is_ok_and(|mut entries| entries.next().is_none())
+    }
+
+    fn check(&self, requirement: PathRequirement) -> Result<(), PathIssue> {
+        check::check_path(self.as_ref(), requirement)
+    }
+
     /// Find the innermost existing file/parent in the path
     fn largest_valid_subset(&'a self) -> Option<Self::P1> {
         let mut path = self.as_ref();
@@ -111,6 +199,57 @@ where
         }
     }
 
+    fn is_file(&self) -> bool {
+        match self {
+            Some(p) => p.is_file(),
+            None => false,
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        match self {
+            Some(p) => p.is_dir(),
+            None => false,
+        }
+    }
+
+    fn is_symlink(&self) -> bool {
+        match self {
+            Some(p) => p.is_symlink(),
+            None => false,
+        }
+    }
+
+    #[cfg(unix)]
+    fn is_owned_by_current_user(&self) -> bool {
+        match self {
+            Some(p) => p.is_owned_by_current_user(),
+            None => false,
+        }
+    }
+
+    #[cfg(unix)]
+    fn is_private(&self) -> bool {
+        match self {
+            Some(p) => p.is_private(),
+            None => false,
+        }
+    }
+
+    fn is_empty_dir(&self) -> bool {
+        match self {
+            Some(p) => p.is_empty_dir(),
+            None => false,
+        }
+    }
+
+    fn check(&self, requirement: PathRequirement) -> Result<(), PathIssue> {
+        match self {
+            Some(p) => p.check(requirement),
+            None => Err(PathIssue::NoPath),
+        }
+    }
+
     fn largest_valid_subset(&'a self) -> Option<Self::P1> {
         match self {
             Some(p) => p.largest_valid_subset(),
@@ -125,7 +264,7 @@ where
 /// - [`'a`]: The lifetime of an output `Path`.
 /// - [`P`]: The path-like type on which the methods act.
 /// - [`Q`]: The input path-like type. It could , for example, represent an
-/// optional path-like object.
+///   optional path-like object.
 pub trait ValidPaths<'a, P, Q>
 where
     P: AsRef<Path> + 'a,
@@ -150,9 +289,25 @@ where
 
     fn all_creatable_paths(&'a mut self) -> Box<dyn Iterator<Item = P> + 'a>;
 
-    fn first_valid_path(&'a mut self, f: fn(&Q) -> bool) -> Option<P>;
-
-    fn all_valid_paths(&'a mut self, f: fn(&Q) -> bool) -> Box<dyn Iterator<Item = P> + 'a>;
+    /// The first path for which the predicate holds. The predicate can be a
+    /// method of [`ValidPath`] (like `PathBuf::is_dir`), a closure capturing
+    /// context, or a combination of both (see [`PathPredicate`]). Being
+    /// generic, it cannot be called on a `dyn ValidPaths`.
+    fn first_valid_path<F>(&'a mut self, f: F) -> Option<P>
+    where
+        Self: Sized,
+        F: FnMut(&Q) -> bool;
+
+    /// Like [`first_valid_path()`](ValidPaths::first_valid_path), but for
+    /// every path for which the predicate holds.
+    fn all_valid_paths<F>(&'a mut self, f: F) -> Box<dyn Iterator<Item = P> + 'a>
+    where
+        Self: Sized,
+        F: FnMut(&Q) -> bool + 'a;
+
+    /// Check every candidate for the requirement, and report the valid ones
+    /// together with the reasons why the others were skipped.
+    fn check_paths(&mut self, requirement: PathRequirement) -> PathReport<P>;
 }
 
 /// Implement for iterators of objects that can be converted to &Path.
@@ -201,12 +356,25 @@ where
         self.all_valid_paths(P::is_creatable)
     }
 
-    fn first_valid_path(&mut self, f: fn(&P) -> bool) -> Option<P> {
-        self.find(|p| f(p))
+    fn first_valid_path<F>(&mut self, f: F) -> Option<P>
+    where
+        F: FnMut(&P) -> bool,
+    {
+        self.find(f)
+    }
+
+    fn all_valid_paths<F>(&'a mut self, f: F) -> Box<dyn Iterator<Item = P> + 'a>
+    where
+        F: FnMut(&P) -> bool + 'a,
+    {
+        Box::new(self.filter(f))
     }
 
-    fn all_valid_paths(&'a mut self, f: fn(&P) -> bool) -> Box<dyn Iterator<Item = P> + 'a> {
-        Box::new(self.filter(move |p| f(p)))
+    fn check_paths(&mut self, requirement: PathRequirement) -> PathReport<P> {
+        PathReport::new(
+            self.map(|path| path.check(requirement).map(|()| path))
+                .collect(),
+        )
     }
 }
 
@@ -256,24 +424,435 @@ where
         self.all_valid_paths(Option::<P>::is_creatable)
     }
 
-    fn first_valid_path(&mut self, f: fn(&Option<P>) -> bool) -> Option<P> {
-        self.find(|p| f(p))
-            .flatten()
+    fn first_valid_path<F>(&mut self, f: F) -> Option<P>
+    where
+        F: FnMut(&Option<P>) -> bool,
+    {
+        self.find(f).flatten()
+    }
+
+    fn all_valid_paths<F>(&'a mut self, f: F) -> Box<dyn Iterator<Item = P> + 'a>
+    where
+        F: FnMut(&Option<P>) -> bool + 'a,
+    {
+        Box::new(self.filter(f).flatten())
     }
 
-    fn all_valid_paths(
-        &'a mut self,
-        f: fn(&Option<P>) -> bool,
-    ) -> Box<dyn Iterator<Item = P> + 'a> {
-        Box::new(
-            self.filter(move |p| f(p))
-                .flat_map(convert::identity),
+    fn check_paths(&mut self, requirement: PathRequirement) -> PathReport<P> {
+        PathReport::new(
+            self.map(|path| match path {
+                Some(path) => path.check(requirement).map(|()| path),
+                None => Err(PathIssue::NoPath),
+            })
+            .collect(),
         )
     }
 }
 
+/// Combinators for predicates on paths, like the methods of [`ValidPath`],
+/// to be passed to [`ValidPaths::first_valid_path()`] and
+/// [`ValidPaths::all_valid_paths()`]. For example:
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use dapp::path::{PathPredicate, ValidPath, ValidPaths};
+/// # let candidates = [PathBuf::from("/var/lib/myapp"), PathBuf::from("/srv/myapp")];
+/// let data_dir = candidates.into_iter().first_valid_path(
+///     PathBuf::is_dir
+///         .and(PathBuf::is_writable)
+///         .and(PathBuf::is_symlink.not())
+///         .and(|path: &PathBuf| path.join("index.db").exists()),
+/// );
+/// ```
+pub trait PathPredicate<Q>: FnMut(&Q) -> bool + Sized {
+    /// Holds if both predicates hold. The second one is only evaluated if
+    /// the first one holds.
+    fn and<G>(mut self, mut other: G) -> impl FnMut(&Q) -> bool
+    where
+        G: FnMut(&Q) -> bool,
+    {
+        move |path| self(path) && other(path)
+    }
+
+    /// Holds if either predicate holds. The second one is only evaluated if
+    /// the first one does not hold.
+    fn or<G>(mut self, mut other: G) -> impl FnMut(&Q) -> bool
+    where
+        G: FnMut(&Q) -> bool,
+    {
+        move |path| self(path) || other(path)
+    }
+
+    /// Holds if the predicate does not hold.
+    fn not(mut self) -> impl FnMut(&Q) -> bool {
+        move |path| !self(path)
+    }
+}
+
+impl<Q, F> PathPredicate<Q> for F where F: FnMut(&Q) -> bool {}
+
+// region: SECURITY
+
+#[cfg(unix)]
+/// A reason why a file (for example, a config file) could be modified by a
+/// user other than the current one. Each reason explains how to fix it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Insecurity {
+    /// The file, or a directory containing it, is owned by a user other than
+    /// the current user or root (or, for a runtime directory, other than the
+    /// current user).
+    NotOwned { path: PathBuf, owner: u32 },
+    /// The file can be written by its group or by other users.
+    WritableByOthers { path: PathBuf, mode: u32 },
+    /// A directory containing the file can be written by its group or by
+    /// other users, who could replace the file. Directories with the sticky
+    /// bit (like `/tmp`) are not reported, since other users cannot replace
+    /// files they do not own in them.
+    DirectoryWritableByOthers { path: PathBuf, mode: u32 },
+    /// A runtime directory can be accessed by its group or by other users,
+    /// while the XDG Base Directory Specification requires mode 0700.
+    NotPrivate { path: PathBuf, mode: u32 },
+}
+
+#[cfg(unix)]
+impl fmt::Display for Insecurity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Insecurity::NotOwned { path, owner } => write!(
+                f,
+                "{:?} is owned by the user with UID {}, fix with `chown $(id -u) {}`",
+                path,
+                owner,
+                path.display()
+            ),
+            Insecurity::WritableByOthers { path, mode } => write!(
+                f,
+                "{:?} can be written by other users (mode {:03o}), fix with `chmod 600 {}`",
+                path,
+                mode & 0o777,
+                path.display()
+            ),
+            Insecurity::DirectoryWritableByOthers { path, mode } => write!(
+                f,
+                "the directory {:?} can be written by other users (mode {:03o}), fix with \
+                 `chmod go-w {}`",
+                path,
+                mode & 0o777,
+                path.display()
+            ),
+            Insecurity::NotPrivate { path, mode } => write!(
+                f,
+                "{:?} can be accessed by other users (mode {:03o}), fix with `chmod 700 {}`",
+                path,
+                mode & 0o777,
+                path.display()
+            ),
+        }
+    }
+}
+
+#[cfg(unix)]
+/// Find every reason why the file at `path` could be modified by a user
+/// other than the current one, like `ssh` does for its config files: the
+/// file and every directory containing it must be owned by the current user
+/// or root, the file must not be writable by its group or other users, and
+/// neither may the directories, unless they have the sticky bit. An empty
+/// list means that the file is secure. Symbolic links are followed, and the
+/// directories containing the target are checked.
+pub fn find_insecurities(path: impl AsRef<Path>) -> io::Result<Vec<Insecurity>> {
+    let path = fs::canonicalize(path)?;
+    let metadata = fs::metadata(&path)?;
+    find_file_insecurities(&path, &metadata)
+}
+
+#[cfg(unix)]
+/// Like [`find_insecurities()`], for the file at the canonical `path` whose
+/// metadata was already read, like from an open file, so that the file
+/// checked is the file read.
+pub(crate) fn find_file_insecurities(
+    path: &Path,
+    metadata: &fs::Metadata,
+) -> io::Result<Vec<Insecurity>> {
+    // SAFETY: `geteuid()` cannot fail and has no preconditions
+    let current_user = unsafe { libc::geteuid() };
+    let is_trusted_owner = |owner: u32| owner == current_user || owner == 0;
+
+    let mut insecurities = Vec::new();
+    if !is_trusted_owner(metadata.uid()) {
+        insecurities.push(Insecurity::NotOwned {
+            path: path.to_owned(),
+            owner: metadata.uid(),
+        });
+    }
+    if metadata.mode() & 0o022 != 0 {
+        insecurities.push(Insecurity::WritableByOthers {
+            path: path.to_owned(),
+            mode: metadata.mode(),
+        });
+    }
+    for directory in path.ancestors().skip(1) {
+        let metadata = fs::metadata(directory)?;
+        if !is_trusted_owner(metadata.uid()) {
+            insecurities.push(Insecurity::NotOwned {
+                path: directory.to_owned(),
+                owner: metadata.uid(),
+            });
+        }
+        if metadata.mode() & 0o022 != 0 && metadata.mode() & 0o1000 == 0 {
+            insecurities.push(Insecurity::DirectoryWritableByOthers {
+                path: directory.to_owned(),
+                mode: metadata.mode(),
+            });
+        }
+    }
+    Ok(insecurities)
+}
+
+#[cfg(unix)]
+/// Find every reason why the directory at `path` is not a valid runtime
+/// directory according to the XDG Base Directory Specification, which
+/// requires it to be owned by the current user, with mode 0700. Unlike
+/// [`ValidPath::is_writable()`], this reads the owner and mode of the
+/// directory itself. An empty list means that the directory is valid.
+///
+/// A symbolic link is an error rather than followed, since another user may
+/// have planted it to a directory of theirs, and the directory is checked
+/// through an open descriptor, so that it cannot be swapped after the check.
+pub fn find_runtime_dir_insecurities(path: impl AsRef<Path>) -> io::Result<Vec<Insecurity>> {
+    let path = path.as_ref();
+    // SAFETY: `geteuid()` cannot fail and has no preconditions
+    let current_user = unsafe { libc::geteuid() };
+
+    let directory = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_DIRECTORY | libc::O_CLOEXEC)
+        .open(path)
+        .map_err(|error| match error.raw_os_error() {
+            Some(libc::ELOOP) => io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{:?} is a symbolic link", path),
+            ),
+            Some(libc::ENOTDIR) => io::Error::new(
+                io::ErrorKind::NotADirectory,
+                format!("{:?} is not a directory", path),
+            ),
+            _ => error,
+        })?;
+    let metadata = directory.metadata()?;
+    let mut insecurities = Vec::new();
+    if metadata.uid() != current_user {
+        insecurities.push(Insecurity::NotOwned {
+            path: path.to_owned(),
+            owner: metadata.uid(),
+        });
+    }
+    if metadata.mode() & 0o777 != 0o700 {
+        insecurities.push(Insecurity::NotPrivate {
+            path: path.to_owned(),
+            mode: metadata.mode(),
+        });
+    }
+    Ok(insecurities)
+}
+
+// endregion: SECURITY
+
+// region: ERRORS
+
+/// The error returned when resolving or creating the directories of an
+/// application, or files in them.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Error {
+    #[cfg(unix)]
+    #[non_exhaustive]
+    #[snafu(
+        display("could not find the XDG base directories: {source}"),
+        visibility(pub(crate))
+    )]
+    FindBaseDirectories { source: xdg::BaseDirectoriesError },
+
+    #[cfg(unix)]
+    #[non_exhaustive]
+    #[snafu(
+        display(
+            "there is no runtime directory, since $XDG_RUNTIME_DIR is unset or insecure, \
+             and the fallback directory could not be created securely"
+        ),
+        visibility(pub(crate))
+    )]
+    FindRuntimeDirectory,
+
+    #[non_exhaustive]
+    #[snafu(
+        display("could not create the directory {:?}: {source}", path),
+        visibility(pub(crate))
+    )]
+    CreateDirectory {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[non_exhaustive]
+    #[snafu(
+        display(
+            "could not create the file {:?}, because of {:?}: {source}",
+            path,
+            component
+        ),
+        visibility(pub(crate))
+    )]
+    CreateFile {
+        path: PathBuf,
+        component: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("could not write the file {:?}: {source}", path),
+        visibility(pub(crate))
+    )]
+    WriteFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("could not lock the file {:?}: {source}", path),
+        visibility(pub(crate))
+    )]
+    Lock {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+// endregion: ERRORS
+
 // region: IMPORTS
 
-use std::{convert, path::Path};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use snafu::Snafu;
+
+#[cfg(unix)]
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io,
+    os::unix::fs::{MetadataExt, OpenOptionsExt},
+};
 
 // endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(all(test, unix))]
+mod tests {
+    #[test]
+    fn predicates() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let file = test_dir.path().join("file");
+        let link = test_dir.path().join("link");
+        let empty_dir = test_dir.path().join("empty");
+        fs::write(&file, "").unwrap();
+        symlink(&file, &link).unwrap();
+        fs::create_dir(&empty_dir).unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o600)).unwrap();
+        fs::set_permissions(&empty_dir, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(file.is_file() && !file.is_dir() && !file.is_symlink());
+        assert!(link.is_file() && link.is_symlink());
+        assert!(empty_dir.is_empty_dir() && !test_dir.path().is_empty_dir());
+        assert!(!file.is_empty_dir());
+        assert!(file.is_owned_by_current_user());
+        assert!(file.is_private() && link.is_private() && !empty_dir.is_private());
+        assert!(!Option::<PathBuf>::None.is_file());
+        assert!(Some(&empty_dir).is_dir());
+
+        let candidates = || [empty_dir.clone(), link.clone(), file.clone()].into_iter();
+        assert_eq!(
+            candidates().first_valid_path(PathBuf::is_private),
+            Some(link.clone())
+        );
+        assert_eq!(
+            candidates()
+                .all_valid_paths(|path| !path.is_symlink() && path.is_file())
+                .collect::<Vec<_>>(),
+            vec![file.clone()]
+        );
+        let mut candidates = [None, Some(file), Some(empty_dir.clone())].into_iter();
+        assert_eq!(
+            candidates.first_valid_path(Option::<PathBuf>::is_empty_dir),
+            Some(empty_dir)
+        );
+    }
+
+    #[test]
+    fn predicates_combined() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let config_dir = test_dir.path().join("config");
+        let link = test_dir.path().join("link");
+        fs::create_dir(&config_dir).unwrap();
+        fs::write(config_dir.join("config.toml"), "").unwrap();
+        symlink(&config_dir, &link).unwrap();
+
+        let filename = String::from("config.toml");
+        let contains_file = |path: &PathBuf| path.join(&filename).is_file();
+        let candidates =
+            || [test_dir.path().to_owned(), link.clone(), config_dir.clone()].into_iter();
+        assert_eq!(
+            candidates().first_valid_path(contains_file),
+            Some(link.clone())
+        );
+        assert_eq!(
+            candidates().first_valid_path(PathBuf::is_symlink.not().and(contains_file)),
+            Some(config_dir.clone())
+        );
+        assert_eq!(
+            candidates()
+                .all_valid_paths(PathBuf::is_symlink.or(PathBuf::is_empty_dir.not()))
+                .count(),
+            3
+        );
+        // Predicates can keep state
+        let mut checked = 0;
+        let is_checked = |_: &PathBuf| {
+            checked += 1;
+            true
+        };
+        assert_eq!(
+            candidates().first_valid_path(is_checked.and(PathBuf::is_symlink)),
+            Some(link.clone())
+        );
+        assert_eq!(checked, 2);
+        // The trait stays dyn-compatible
+        let dyn_candidates: &mut dyn ValidPaths<PathBuf, PathBuf> = &mut candidates();
+        assert_eq!(
+            dyn_candidates.first_existing_path(),
+            Some(test_dir.path().to_owned())
+        );
+
+        let mut candidates = [None, Some(link)].into_iter();
+        assert_eq!(
+            candidates.first_valid_path(Option::<PathBuf>::is_dir.and(|path| path.is_some())),
+            Some(test_dir.path().join("link"))
+        );
+    }
+
+    // region: IMPORTS
+
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    use super::*;
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
@@ -0,0 +1,776 @@
+/// The directories of an application, resolved following the XDG Base
+/// Directory Specification:
+/// - The config, data, cache and state directories are the application's
+///   subdirectories of `$XDG_CONFIG_HOME`, `$XDG_DATA_HOME`,
+///   `$XDG_CACHE_HOME` and `$XDG_STATE_HOME`, or of their defaults (like
+///   `~/.config`) when those are unset or not absolute.
+/// - The runtime directory is the application's subdirectory of
+///   `$XDG_RUNTIME_DIR`, which must be owned by the user with mode 0700. If
+///   it is unset or insecure, a warning is logged and a private directory
+///   named after the user's UID in the temporary directory (like
+///   `/tmp/runtime-1000`) is used instead.
+/// - Config and data files are also searched for in the application's
+///   subdirectories of every directory of `$XDG_CONFIG_DIRS` and
+///   `$XDG_DATA_DIRS`, after the user's own directory.
+///
+/// The XDG directories can be replaced, in order of precedence, by:
+/// - An environment variable for a single directory, like
+///   `MYAPP_CONFIG_DIR` for the application `myapp` (see [`DirKind`]).
+/// - An environment variable for all the directories, like `MYAPP_HOME`,
+///   under which the directories are `config`, `data`, `cache` and `state`.
+/// - Portable mode, in which the directories are the same subdirectories of
+///   the directory of the executable, if it contains the
+///   [`PORTABLE_MARKER`](AppDirs::PORTABLE_MARKER) file. This is meant for
+///   applications which run from a self-contained directory, like a USB
+///   stick.
+///
+/// Like the XDG variables, these variables are ignored (with a warning) when
+/// they are not absolute.
+///
+/// With `MYAPP_HOME` and in portable mode, the directories of
+/// `$XDG_CONFIG_DIRS` and `$XDG_DATA_DIRS` are not searched, and the runtime
+/// directory is still the XDG one (unless it is overridden itself), since
+/// sockets cannot be created on many removable filesystems. An overridden
+/// runtime directory must be owned by the user with mode 0700 too, or a
+/// warning is logged and it is ignored. How the
+/// directories were resolved is reported by [`strategy()`] and [`origin()`].
+///
+/// The environment is read once, when the directories are resolved. As is
+/// customary on XDG systems, only the name of the application is used for
+/// its directories. The organization and qualifier (like `example` and
+/// `com`) only make up the [`identifier()`](AppDirs::identifier).
+///
+/// [`strategy()`]: AppDirs::strategy
+/// [`origin()`]: AppDirs::origin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppDirs {
+    app_name: String,
+    organization: Option<String>,
+    qualifier: Option<String>,
+    strategy: DirOrigin,
+    config_home: (PathBuf, DirOrigin),
+    data_home: (PathBuf, DirOrigin),
+    cache_home: (PathBuf, DirOrigin),
+    state_home: (PathBuf, DirOrigin),
+    runtime_dir: Option<(PathBuf, DirOrigin)>,
+    config_dirs: Vec<PathBuf>,
+    data_dirs: Vec<PathBuf>,
+}
+
+/// A kind of application directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DirKind {
+    Config,
+    Data,
+    Cache,
+    State,
+    Runtime,
+}
+
+impl DirKind {
+    /// Every kind of application directory.
+    pub const ALL: [DirKind; 5] = [
+        DirKind::Config,
+        DirKind::Data,
+        DirKind::Cache,
+        DirKind::State,
+        DirKind::Runtime,
+    ];
+
+    /// The name of the kind, like `config`, which is also the name of the
+    /// directory under `MYAPP_HOME` and in portable mode.
+    pub fn name(self) -> &'static str {
+        match self {
+            DirKind::Config => "config",
+            DirKind::Data => "data",
+            DirKind::Cache => "cache",
+            DirKind::State => "state",
+            DirKind::Runtime => "runtime",
+        }
+    }
+
+    /// The environment variable overriding the directory of this kind for
+    /// applications with the given variable prefix, like `MYAPP_CONFIG_DIR`.
+    pub fn var_name(self, prefix: &str) -> String {
+        format!("{}_{}_DIR", prefix, self.name().to_uppercase())
+    }
+}
+
+impl fmt::Display for DirKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How an application directory, or all of them, were resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DirOrigin {
+    /// From the XDG environment variables, or their defaults.
+    Xdg,
+    /// Relative to the executable, since the marker file exists.
+    Portable { marker: PathBuf },
+    /// Under the directory set by an environment variable like `MYAPP_HOME`.
+    Home { variable: String },
+    /// Set by an environment variable like `MYAPP_CONFIG_DIR`.
+    Override { variable: String },
+    /// A private directory in the temporary directory, since
+    /// `$XDG_RUNTIME_DIR` is unset or insecure.
+    Fallback,
+}
+
+impl fmt::Display for DirOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DirOrigin::Xdg => f.write_str("XDG base directories"),
+            DirOrigin::Portable { marker } => write!(f, "portable mode (found {:?})", marker),
+            DirOrigin::Home { variable } => write!(f, "${}", variable),
+            DirOrigin::Override { variable } => write!(f, "${}", variable),
+            DirOrigin::Fallback => {
+                f.write_str("fallback, since $XDG_RUNTIME_DIR is unset or insecure")
+            }
+        }
+    }
+}
+
+impl AppDirs {
+    /// The name of the file which, next to the executable, enables portable
+    /// mode.
+    pub const PORTABLE_MARKER: &'static str = "portable.marker";
+
+    /// Resolve the directories of the application with the given name. This
+    /// fails if the home directory of the user cannot be found (and is
+    /// needed).
+    pub fn new(app_name: impl Into<String>) -> Result<Self, Error> {
+        let executable = env::current_exe().ok();
+        Self::resolve(
+            app_name.into(),
+            executable.as_deref().and_then(Path::parent),
+        )
+    }
+
+    /// The prefix of the environment variables which override the
+    /// directories of an application: its name in uppercase, with every
+    /// character other than letters and digits replaced by `_`. For example,
+    /// `MY_APP` for `my-app`.
+    pub fn env_prefix(app_name: &str) -> String {
+        app_name
+            .chars()
+            .map(|character| match character.is_ascii_alphanumeric() {
+                true => character.to_ascii_uppercase(),
+                false => '_',
+            })
+            .collect()
+    }
+
+    fn resolve(app_name: String, executable_dir: Option<&Path>) -> Result<Self, Error> {
+        let prefix = Self::env_prefix(&app_name);
+        // Like in the XDG variables, relative paths are invalid
+        let var = |name: &str| {
+            let path = PathBuf::from(env::var_os(name)?);
+            if path.is_relative() {
+                warn!(?path, "${name} is ignored, since it is not absolute");
+                return None;
+            }
+            Some(path)
+        };
+
+        let home_variable = format!("{}_HOME", prefix);
+        let base = if let Some(home) = var(&home_variable) {
+            Some((
+                home,
+                DirOrigin::Home {
+                    variable: home_variable,
+                },
+            ))
+        } else {
+            let marker = executable_dir.map(|directory| directory.join(Self::PORTABLE_MARKER));
+            executable_dir
+                .zip(marker)
+                .filter(|(_, marker)| marker.exists())
+                .map(|(directory, marker)| (directory.to_owned(), DirOrigin::Portable { marker }))
+        };
+        let strategy = match &base {
+            Some((_, origin)) => origin.clone(),
+            None => DirOrigin::Xdg,
+        };
+
+        let xdg = BaseDirectories::new().context(FindBaseDirectoriesSnafu)?;
+        // Directories which are not overridden are XDG directories
+        let overridden = |kind: DirKind| {
+            let variable = kind.var_name(&prefix);
+            if let Some(directory) = var(&variable) {
+                return Some((directory, DirOrigin::Override { variable }));
+            }
+            match &base {
+                Some((root, origin)) if kind != DirKind::Runtime => {
+                    Some((root.join(kind.name()), origin.clone()))
+                }
+                _ => None,
+            }
+        };
+        let xdg_dir = |directory: &Path| (directory.join(&app_name), DirOrigin::Xdg);
+        let resolve_home =
+            |kind: DirKind, home: PathBuf| overridden(kind).unwrap_or_else(|| xdg_dir(&home));
+        let config_home = resolve_home(DirKind::Config, xdg.get_config_home());
+        let data_home = resolve_home(DirKind::Data, xdg.get_data_home());
+        let cache_home = resolve_home(DirKind::Cache, xdg.get_cache_home());
+        let state_home = resolve_home(DirKind::State, xdg.get_state_home());
+        // An overridden runtime directory must be as private as the XDG one
+        let runtime_dir = overridden(DirKind::Runtime)
+            .filter(|(directory, _)| {
+                is_secure_runtime_dir(&DirKind::Runtime.var_name(&prefix), directory)
+            })
+            .or_else(|| {
+                default_runtime_dir().map(|(directory, origin)| (directory.join(&app_name), origin))
+            });
+        let app_dirs = |directories: Vec<PathBuf>| match base.is_some() {
+            true => Vec::new(),
+            false => directories
+                .into_iter()
+                .map(|directory| directory.join(&app_name))
+                .collect(),
+        };
+        let config_dirs = app_dirs(xdg.get_config_dirs());
+        let data_dirs = app_dirs(xdg.get_data_dirs());
+
+        Ok(Self {
+            strategy,
+            config_home,
+            data_home,
+            cache_home,
+            state_home,
+            runtime_dir,
+            config_dirs,
+            data_dirs,
+            organization: None,
+            qualifier: None,
+            app_name,
+        })
+    }
+
+    /// Set the organization developing the application, like `example`.
+    pub fn organization(mut self, organization: impl Into<String>) -> Self {
+        self.organization = Some(organization.into());
+        self
+    }
+
+    /// Set the qualifier of the organization, like `com` or `org`.
+    pub fn qualifier(mut self, qualifier: impl Into<String>) -> Self {
+        self.qualifier = Some(qualifier.into());
+        self
+    }
+
+    pub fn app_name(&self) -> &str {
+        &self.app_name
+    }
+
+    /// The reverse-domain identifier of the application, like
+    /// `com.example.myapp`, made of the qualifier, the organization and the
+    /// name of the application, as far as they are set.
+    pub fn identifier(&self) -> String {
+        [&self.qualifier, &self.organization]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .chain([self.app_name.as_str()])
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// How the directories were resolved as a whole: from the XDG
+    /// environment variables, from a variable like `MYAPP_HOME`, or in
+    /// portable mode. Single directories may still be overridden, see
+    /// [`origin()`](AppDirs::origin).
+    pub fn strategy(&self) -> &DirOrigin {
+        &self.strategy
+    }
+
+    /// How the directory of the given kind was resolved, or `None` for a
+    /// missing runtime directory.
+    pub fn origin(&self, kind: DirKind) -> Option<&DirOrigin> {
+        self.get(kind).map(|(_, origin)| origin)
+    }
+
+    /// The directory of the given kind, or `None` for a missing runtime
+    /// directory.
+    pub fn dir(&self, kind: DirKind) -> Option<&Path> {
+        self.get(kind).map(|(directory, _)| directory.as_path())
+    }
+
+    fn get(&self, kind: DirKind) -> Option<&(PathBuf, DirOrigin)> {
+        match kind {
+            DirKind::Config => Some(&self.config_home),
+            DirKind::Data => Some(&self.data_home),
+            DirKind::Cache => Some(&self.cache_home),
+            DirKind::State => Some(&self.state_home),
+            DirKind::Runtime => self.runtime_dir.as_ref(),
+        }
+    }
+
+    /// The directory for the user's config files, like `~/.config/myapp`.
+    pub fn config_dir(&self) -> &Path {
+        &self.config_home.0
+    }
+
+    /// The directory for the user's data files, like `~/.local/share/myapp`.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_home.0
+    }
+
+    /// The directory for non-essential cached files, like `~/.cache/myapp`.
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_home.0
+    }
+
+    /// The directory for state which persists between runs but is not worth
+    /// backing up, like logs and history, like `~/.local/state/myapp`.
+    pub fn state_dir(&self) -> &Path {
+        &self.state_home.0
+    }
+
+    /// The directory for runtime files like sockets and locks, like
+    /// `/run/user/1000/myapp`, or `None` if neither `$XDG_RUNTIME_DIR` nor
+    /// the fallback directory are private to the user.
+    pub fn runtime_dir(&self) -> Option<&Path> {
+        self.dir(DirKind::Runtime)
+    }
+
+    /// The directories in which config files are searched for, in order of
+    /// precedence: the user's config directory, and then the application's
+    /// subdirectory of every directory of `$XDG_CONFIG_DIRS`.
+    pub fn config_dirs(&self) -> impl Iterator<Item = &Path> {
+        iter::once(self.config_dir()).chain(self.config_dirs.iter().map(PathBuf::as_path))
+    }
+
+    /// Like [`config_dirs()`](AppDirs::config_dirs), but for data files and
+    /// `$XDG_DATA_DIRS`.
+    pub fn data_dirs(&self) -> impl Iterator<Item = &Path> {
+        iter::once(self.data_dir()).chain(self.data_dirs.iter().map(PathBuf::as_path))
+    }
+
+    /// The readable config file (not directory) with the given name (which may contain
+    /// subdirectories, like `themes/dark.toml`) with the highest precedence.
+    pub fn find_config_file(&self, name: impl AsRef<Path>) -> Option<PathBuf> {
+        self.config_dirs()
+            .map(|directory| directory.join(name.as_ref()))
+            .first_valid_path(PathBuf::is_file.and(PathBuf::is_readable))
+    }
+
+    /// Every readable config file with the given name, in order of
+    /// precedence, for example, to merge them.
+    pub fn find_config_files(&self, name: impl AsRef<Path>) -> Vec<PathBuf> {
+        let mut candidates = self
+            .config_dirs()
+            .map(|directory| directory.join(name.as_ref()));
+        candidates
+            .all_valid_paths(PathBuf::is_file.and(PathBuf::is_readable))
+            .collect()
+    }
+
+    /// Like [`find_config_file()`](AppDirs::find_config_file), but for data
+    /// files.
+    pub fn find_data_file(&self, name: impl AsRef<Path>) -> Option<PathBuf> {
+        self.data_dirs()
+            .map(|directory| directory.join(name.as_ref()))
+            .first_valid_path(PathBuf::is_file.and(PathBuf::is_readable))
+    }
+
+    /// The filepath at which to write the config file with the given name
+    /// (which may contain subdirectories), in the user's config directory.
+    /// Missing parent directories are created.
+    pub fn place_config_file(&self, name: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        place_file(self.config_dir(), name.as_ref(), false)
+    }
+
+    /// Like [`place_config_file()`](AppDirs::place_config_file), but in the
+    /// data directory.
+    pub fn place_data_file(&self, name: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        place_file(self.data_dir(), name.as_ref(), false)
+    }
+
+    /// Like [`place_config_file()`](AppDirs::place_config_file), but in the
+    /// cache directory.
+    pub fn place_cache_file(&self, name: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        place_file(self.cache_dir(), name.as_ref(), false)
+    }
+
+    /// Like [`place_config_file()`](AppDirs::place_config_file), but in the
+    /// state directory. Missing directories are created private to the user
+    /// (mode 0700), since state like history can be sensitive.
+    pub fn place_state_file(&self, name: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        place_file(self.state_dir(), name.as_ref(), true)
+    }
+
+    /// Like [`place_config_file()`](AppDirs::place_config_file), but in the
+    /// runtime directory, which must be available. Missing directories are
+    /// created private to the user (mode 0700).
+    pub fn place_runtime_file(&self, name: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        let runtime_dir = self.runtime_dir().context(FindRuntimeDirectorySnafu)?;
+        place_file(runtime_dir, name.as_ref(), true)
+    }
+
+    /// The lock file for the file with the given name (which may contain
+    /// subdirectories, like `state/history`), in the `locks` subdirectory of
+    /// the runtime directory, like `/run/user/1000/myapp/locks/history.lock`.
+    /// This is the convention for [`FileLock`]s shared by the processes of
+    /// the application, so that, for example, readers and writers of the
+    /// same state file coordinate. The runtime directory must be available.
+    ///
+    /// [`FileLock`]: super::FileLock
+    pub fn lock_path(&self, name: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        let mut lock_name = Path::new("locks").join(name).into_os_string();
+        lock_name.push(".lock");
+        self.place_runtime_file(lock_name)
+    }
+}
+
+/// Join a filename to a directory, creating the missing parent directories,
+/// with mode 0700 if they should be private.
+fn place_file(directory: &Path, name: &Path, private: bool) -> Result<PathBuf, Error> {
+    let path = directory.join(name);
+    match path.parent() {
+        Some(parent) if private => ensure_dir(parent, 0o700)?,
+        Some(parent) => {
+            fs::create_dir_all(parent).context(CreateDirectorySnafu { path: parent })?
+        }
+        None => {}
+    }
+    Ok(path)
+}
+
+/// The runtime directory when it is not overridden: `$XDG_RUNTIME_DIR`, or
+/// the fallback runtime directory if it is unset or insecure.
+fn default_runtime_dir() -> Option<(PathBuf, DirOrigin)> {
+    match xdg_runtime_dir() {
+        Some(directory) => Some((directory, DirOrigin::Xdg)),
+        None => fallback_runtime_dir()
+            .inspect_err(|error| warn!("could not create a fallback runtime directory: {error}"))
+            .ok()
+            .map(|directory| (directory, DirOrigin::Fallback)),
+    }
+}
+
+/// `$XDG_RUNTIME_DIR`, if it is set to a directory owned by the user with
+/// mode 0700. The reason why it cannot be used is logged otherwise.
+fn xdg_runtime_dir() -> Option<PathBuf> {
+    let directory = env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+    let Some(directory) = directory.filter(|directory| directory.is_absolute()) else {
+        warn!("$XDG_RUNTIME_DIR is unset or not absolute");
+        return None;
+    };
+    is_secure_runtime_dir("XDG_RUNTIME_DIR", &directory).then_some(directory)
+}
+
+/// Whether the runtime directory set by the environment variable is owned by
+/// the user with mode 0700. The reason why it cannot be used is logged
+/// otherwise.
+fn is_secure_runtime_dir(variable: &str, directory: &Path) -> bool {
+    match find_runtime_dir_insecurities(directory) {
+        Ok(insecurities) if insecurities.is_empty() => true,
+        Ok(insecurities) => {
+            for insecurity in insecurities {
+                warn!("${variable} is insecure: {insecurity}");
+            }
+            false
+        }
+        Err(error) => {
+            warn!(path = ?directory, "${variable} cannot be used: {error}");
+            false
+        }
+    }
+}
+
+/// Create the fallback runtime directory of the user, like
+/// `/tmp/runtime-1000`, or check that the existing one is still private to
+/// the user, since anyone can create it first in the temporary directory
+/// (or plant a symbolic link there).
+fn fallback_runtime_dir() -> io::Result<PathBuf> {
+    // SAFETY: `geteuid()` cannot fail and has no preconditions
+    let current_user = unsafe { libc::geteuid() };
+    let directory = env::temp_dir().join(format!("runtime-{}", current_user));
+    match DirBuilder::new().mode(0o700).create(&directory) {
+        // The umask may have removed permissions from the mode
+        Ok(()) => fs::set_permissions(&directory, fs::Permissions::from_mode(0o700))?,
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(error) => return Err(error),
+    }
+    let insecurities = find_runtime_dir_insecurities(&directory)?;
+    if let Some(insecurity) = insecurities.first() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            insecurity.to_string(),
+        ));
+    }
+    warn!(path = ?directory, "using a fallback runtime directory");
+    Ok(directory)
+}
+
+// region: IMPORTS
+
+use std::{
+    env, fmt,
+    fs::{self, DirBuilder},
+    io, iter,
+    os::unix::fs::{DirBuilderExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use snafu::{OptionExt, ResultExt};
+
+use tracing::warn;
+
+use xdg::BaseDirectories;
+
+use super::{
+    ensure_dir, find_runtime_dir_insecurities, CreateDirectorySnafu, Error,
+    FindBaseDirectoriesSnafu, FindRuntimeDirectorySnafu, PathPredicate, ValidPath, ValidPaths,
+};
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn app_dirs() {
+        let _xdg_env = crate::XDG_ENV_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let xdg_vars = [
+            "XDG_CONFIG_HOME",
+            "XDG_CONFIG_DIRS",
+            "XDG_STATE_HOME",
+            "XDG_CACHE_HOME",
+        ]
+        .map(|name| (name, env::var_os(name)));
+        let config_home = tempfile::tempdir().unwrap();
+        let config_dir = tempfile::tempdir().unwrap();
+        let state_home = tempfile::tempdir().unwrap();
+        env::set_var("XDG_CONFIG_HOME", config_home.path());
+        env::set_var("XDG_CONFIG_DIRS", config_dir.path());
+        env::set_var("XDG_STATE_HOME", state_home.path());
+        env::set_var("XDG_CACHE_HOME", "relative/cache");
+
+        let app_dirs = AppDirs::new("dapp-test-dirs")
+            .unwrap()
+            .organization("example")
+            .qualifier("com");
+        assert_eq!(app_dirs.identifier(), "com.example.dapp-test-dirs");
+        assert_eq!(
+            app_dirs.config_dir(),
+            config_home.path().join("dapp-test-dirs")
+        );
+        // Relative paths are invalid, and replaced by the defaults
+        assert!(app_dirs.cache_dir().is_absolute());
+
+        assert_eq!(app_dirs.find_config_file("config.toml"), None);
+        fs::create_dir(config_dir.path().join("dapp-test-dirs")).unwrap();
+        let system_file = config_dir.path().join("dapp-test-dirs/config.toml");
+        fs::write(&system_file, "").unwrap();
+        assert_eq!(
+            app_dirs.find_config_file("config.toml"),
+            Some(system_file.clone())
+        );
+        let user_file = app_dirs.place_config_file("config.toml").unwrap();
+        fs::write(&user_file, "").unwrap();
+        assert_eq!(
+            app_dirs.find_config_files("config.toml"),
+            [user_file, system_file]
+        );
+
+        let state_file = app_dirs.place_state_file("logs/app.log").unwrap();
+        assert_eq!(
+            state_file,
+            state_home.path().join("dapp-test-dirs/logs/app.log")
+        );
+        assert!(state_file.parent().unwrap().is_dir());
+        assert!(state_file.parent().unwrap().is_private());
+
+        for (name, value) in xdg_vars {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+    }
+
+    #[test]
+    fn portable_and_overrides() {
+        let _xdg_env = crate::XDG_ENV_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let executable_dir = tempfile::tempdir().unwrap();
+        let home = tempfile::tempdir().unwrap();
+        let config_dir = tempfile::tempdir().unwrap();
+
+        let app_dirs = AppDirs::resolve(
+            String::from("dapp-test-portable"),
+            Some(executable_dir.path()),
+        )
+        .unwrap();
+        assert_eq!(app_dirs.strategy(), &DirOrigin::Xdg);
+
+        let marker = executable_dir.path().join(AppDirs::PORTABLE_MARKER);
+        fs::write(&marker, "").unwrap();
+        let app_dirs = AppDirs::resolve(
+            String::from("dapp-test-portable"),
+            Some(executable_dir.path()),
+        )
+        .unwrap();
+        assert_eq!(app_dirs.strategy(), &DirOrigin::Portable { marker });
+        assert_eq!(app_dirs.data_dir(), executable_dir.path().join("data"));
+        assert_eq!(app_dirs.config_dirs().count(), 1);
+        assert_ne!(app_dirs.origin(DirKind::Runtime), Some(app_dirs.strategy()));
+
+        assert_eq!(
+            AppDirs::env_prefix("dapp-test-portable"),
+            "DAPP_TEST_PORTABLE"
+        );
+        env::set_var("DAPP_TEST_PORTABLE_HOME", home.path());
+        env::set_var("DAPP_TEST_PORTABLE_CONFIG_DIR", config_dir.path());
+        let app_dirs = AppDirs::resolve(
+            String::from("dapp-test-portable"),
+            Some(executable_dir.path()),
+        )
+        .unwrap();
+        env::remove_var("DAPP_TEST_PORTABLE_HOME");
+        env::remove_var("DAPP_TEST_PORTABLE_CONFIG_DIR");
+        assert_eq!(
+            app_dirs.strategy(),
+            &DirOrigin::Home {
+                variable: String::from("DAPP_TEST_PORTABLE_HOME")
+            }
+        );
+        assert_eq!(app_dirs.state_dir(), home.path().join("state"));
+        assert_eq!(app_dirs.config_dir(), config_dir.path());
+        assert_eq!(
+            app_dirs.origin(DirKind::Config),
+            Some(&DirOrigin::Override {
+                variable: String::from("DAPP_TEST_PORTABLE_CONFIG_DIR")
+            })
+        );
+
+        // Relative paths are ignored
+        env::set_var("DAPP_TEST_PORTABLE_HOME", "relative/home");
+        env::set_var("DAPP_TEST_PORTABLE_CONFIG_DIR", "relative/config");
+        let app_dirs = AppDirs::resolve(
+            String::from("dapp-test-portable"),
+            Some(executable_dir.path()),
+        )
+        .unwrap();
+        env::remove_var("DAPP_TEST_PORTABLE_HOME");
+        env::remove_var("DAPP_TEST_PORTABLE_CONFIG_DIR");
+        assert_eq!(
+            app_dirs.strategy(),
+            &DirOrigin::Portable {
+                marker: executable_dir.path().join(AppDirs::PORTABLE_MARKER)
+            }
+        );
+        assert_eq!(app_dirs.config_dir(), executable_dir.path().join("config"));
+    }
+
+    #[test]
+    fn runtime_dir() {
+        let _xdg_env = crate::XDG_ENV_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let xdg_runtime_dir = env::var_os("XDG_RUNTIME_DIR");
+        let runtime_dir = tempfile::tempdir().unwrap();
+        fs::set_permissions(runtime_dir.path(), fs::Permissions::from_mode(0o700)).unwrap();
+        env::set_var("XDG_RUNTIME_DIR", runtime_dir.path());
+
+        let app_dirs = AppDirs::new("dapp-test-runtime").unwrap();
+        assert_eq!(app_dirs.origin(DirKind::Runtime), Some(&DirOrigin::Xdg));
+        assert_eq!(
+            app_dirs.runtime_dir(),
+            Some(runtime_dir.path().join("dapp-test-runtime").as_path())
+        );
+        assert_eq!(
+            app_dirs.lock_path("state/history").unwrap(),
+            runtime_dir
+                .path()
+                .join("dapp-test-runtime/locks/state/history.lock")
+        );
+        assert!(runtime_dir
+            .path()
+            .join("dapp-test-runtime/locks/state")
+            .is_private());
+
+        env::set_var("DAPP_TEST_RUNTIME_RUNTIME_DIR", runtime_dir.path());
+        let app_dirs = AppDirs::new("dapp-test-runtime").unwrap();
+        env::remove_var("DAPP_TEST_RUNTIME_RUNTIME_DIR");
+        assert_eq!(app_dirs.runtime_dir(), Some(runtime_dir.path()));
+
+        fs::set_permissions(runtime_dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(matches!(
+            &find_runtime_dir_insecurities(runtime_dir.path()).unwrap()[..],
+            [Insecurity::NotPrivate { mode, .. }] if mode & 0o777 == 0o755
+        ));
+        let app_dirs = AppDirs::new("dapp-test-runtime").unwrap();
+        assert_eq!(
+            app_dirs.origin(DirKind::Runtime),
+            Some(&DirOrigin::Fallback)
+        );
+        let fallback_dir = app_dirs.runtime_dir().unwrap().parent().unwrap();
+        assert!(find_runtime_dir_insecurities(fallback_dir)
+            .unwrap()
+            .is_empty());
+
+        // An insecure override is ignored too
+        env::set_var("DAPP_TEST_RUNTIME_RUNTIME_DIR", runtime_dir.path());
+        let app_dirs = AppDirs::new("dapp-test-runtime").unwrap();
+        env::remove_var("DAPP_TEST_RUNTIME_RUNTIME_DIR");
+        assert_eq!(
+            app_dirs.origin(DirKind::Runtime),
+            Some(&DirOrigin::Fallback)
+        );
+
+        env::remove_var("XDG_RUNTIME_DIR");
+        let app_dirs = AppDirs::new("dapp-test-runtime").unwrap();
+        assert_eq!(app_dirs.runtime_dir().unwrap().parent(), Some(fallback_dir));
+        if let Some(xdg_runtime_dir) = xdg_runtime_dir {
+            env::set_var("XDG_RUNTIME_DIR", xdg_runtime_dir);
+        }
+    }
+
+    #[test]
+    fn planted_fallback_runtime_dir() {
+        let _xdg_env = crate::XDG_ENV_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let tmpdir = env::var_os("TMPDIR");
+        let temp_dir = tempfile::tempdir().unwrap();
+        env::set_var("TMPDIR", temp_dir.path());
+
+        // Another user could plant a symbolic link to a directory of theirs,
+        // which would pass the checks if it was followed
+        let target = temp_dir.path().join("target");
+        DirBuilder::new().mode(0o700).create(&target).unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o700)).unwrap();
+        // SAFETY: `geteuid()` cannot fail and has no preconditions
+        let current_user = unsafe { libc::geteuid() };
+        let link = temp_dir.path().join(format!("runtime-{}", current_user));
+        symlink(&target, &link).unwrap();
+        assert!(find_runtime_dir_insecurities(&target).unwrap().is_empty());
+        assert!(find_runtime_dir_insecurities(&link).is_err());
+        let result = fallback_runtime_dir();
+
+        match tmpdir {
+            Some(tmpdir) => env::set_var("TMPDIR", tmpdir),
+            None => env::remove_var("TMPDIR"),
+        }
+        assert!(result.is_err());
+    }
+
+    // region: IMPORTS
+
+    use std::{env, fs, os::unix::fs::symlink, sync::PoisonError};
+
+    use super::{super::Insecurity, *};
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
@@ -0,0 +1,282 @@
+/// A file which replaces the file at a path at once when it is committed,
+/// so that readers (and the file itself, if the system crashes) only ever
+/// see the old or the new contents, and never a partial write:
+/// - It is written to a temporary file next to the file to replace.
+/// - On [`commit()`](AtomicWriter::commit), the temporary file is flushed to
+///   disk, renamed over the file, and the directory containing them is
+///   flushed to disk too, so that the rename itself persists.
+/// - The new file has the permissions of the file it replaces, if any.
+/// - Optionally, the replaced file is kept as a numbered backup, like
+///   `config.toml.bak.1`, and older backups are shifted, like to
+///   `config.toml.bak.2`, up to the given number of backups.
+/// - If the writer is dropped without being committed, including after an
+///   error, the temporary file is deleted and the file is left untouched.
+///
+/// If the path is a symbolic link, the file it points to is replaced, or
+/// created if the link is dangling, so that the link itself is kept.
+///
+/// ```no_run
+/// # use std::io::Write;
+/// # use dapp::path::{AppDirs, AtomicWriter};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let app_dirs = AppDirs::new("myapp")?;
+/// # let history = String::new();
+/// let mut writer = AtomicWriter::new(app_dirs.place_state_file("history")?)?.backups(3);
+/// writer.write_all(history.as_bytes())?;
+/// writer.commit()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AtomicWriter {
+    path: PathBuf,
+    temporary_path: PathBuf,
+    file: File,
+    backups: usize,
+    is_committed: bool,
+}
+
+impl AtomicWriter {
+    /// Start replacing the file at `path`, whose directory must exist. The
+    /// file itself does not have to exist.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let path = fs::canonicalize(path)
+            .or_else(|_| resolve_symlinks(path))
+            .context(WriteFileSnafu { path })?;
+        let mode = fs::metadata(&path)
+            .ok()
+            .map(|metadata| metadata.permissions().mode() & 0o7777);
+
+        // Hidden, and unique even for several writers in the same process
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let mut temporary_name = OsString::from(".");
+        temporary_name.push(path.file_name().unwrap_or_default());
+        temporary_name.push(format!(
+            ".{}.{}.tmp",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temporary_path = path.with_file_name(temporary_name);
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(mode.unwrap_or(0o666))
+            .custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC)
+            .open(&temporary_path)
+            .context(WriteFileSnafu { path: &path })?;
+        let writer = Self {
+            path,
+            temporary_path,
+            file,
+            backups: 0,
+            is_committed: false,
+        };
+        // The umask may have removed permissions from the mode
+        if let Some(mode) = mode {
+            writer
+                .file
+                .set_permissions(Permissions::from_mode(mode))
+                .context(WriteFileSnafu { path: &writer.path })?;
+        }
+        Ok(writer)
+    }
+
+    /// Keep the given number of backups of the replaced file. There are no
+    /// backups by default.
+    pub fn backups(mut self, count: usize) -> Self {
+        self.backups = count;
+        self
+    }
+
+    /// The path of the file which will be replaced.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Replace the file with everything written so far.
+    pub fn commit(mut self) -> Result<(), Error> {
+        let path = self.path.clone();
+        self.file
+            .sync_all()
+            .context(WriteFileSnafu { path: &path })?;
+        if self.backups > 0 && path.exists() {
+            self.rotate_backups()
+                .context(WriteFileSnafu { path: &path })?;
+        }
+        fs::rename(&self.temporary_path, &path).context(WriteFileSnafu { path: &path })?;
+        self.is_committed = true;
+
+        let directory = match path.parent() {
+            Some(directory) if !directory.as_os_str().is_empty() => directory,
+            _ => Path::new("."),
+        };
+        File::open(directory)
+            .and_then(|directory| directory.sync_all())
+            .context(WriteFileSnafu { path: &path })
+    }
+
+    /// The path of the backup with the given number, like `file.bak.1`.
+    pub fn backup_path(&self, number: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".bak.{}", number));
+        PathBuf::from(path)
+    }
+
+    /// Shift the backups, dropping the oldest one, and keep the file which
+    /// is about to be replaced as the first backup. The file is linked
+    /// rather than renamed, so that it exists until it is replaced.
+    fn rotate_backups(&self) -> io::Result<()> {
+        match fs::remove_file(self.backup_path(self.backups)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+        for number in (1..self.backups).rev() {
+            match fs::rename(self.backup_path(number), self.backup_path(number + 1)) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                _ => {}
+            }
+        }
+        let backup_path = self.backup_path(1);
+        fs::hard_link(&self.path, &backup_path)
+            .or_else(|_| fs::copy(&self.path, &backup_path).map(|_| ()))
+    }
+}
+
+/// The path which the symbolic links at `path` lead to, even if the file
+/// they point to does not exist (unlike `fs::canonicalize()`), or `path`
+/// itself if it is not a symbolic link.
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    // Like `SYMLOOP_MAX` on Linux
+    const MAX_SYMLINKS: usize = 40;
+    let mut path = path.to_owned();
+    for _ in 0..MAX_SYMLINKS {
+        match fs::read_link(&path) {
+            // Relative targets are relative to the directory of the link
+            Ok(target) => path = path.parent().unwrap_or(Path::new("")).join(target),
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::InvalidInput | io::ErrorKind::NotFound
+                ) =>
+            {
+                return Ok(path)
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    Err(io::Error::from_raw_os_error(libc::ELOOP))
+}
+
+impl Write for AtomicWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for AtomicWriter {
+    fn drop(&mut self) {
+        if !self.is_committed {
+            let _ = fs::remove_file(&self.temporary_path);
+        }
+    }
+}
+
+// region: IMPORTS
+
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions, Permissions},
+    io::{self, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use snafu::ResultExt;
+
+use super::{Error, WriteFileSnafu};
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn atomic_writer() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let path = test_dir.path().join("state.toml");
+        let write = |contents: &str| {
+            let mut writer = AtomicWriter::new(&path).unwrap().backups(2);
+            writer.write_all(contents.as_bytes()).unwrap();
+            writer.commit().unwrap();
+        };
+
+        write("version = 1");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "version = 1");
+        fs::set_permissions(&path, Permissions::from_mode(0o640)).unwrap();
+        write("version = 2");
+        write("version = 3");
+        write("version = 4");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "version = 4");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+
+        let writer = AtomicWriter::new(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(writer.backup_path(1)).unwrap(),
+            "version = 3"
+        );
+        assert_eq!(
+            fs::read_to_string(writer.backup_path(2)).unwrap(),
+            "version = 2"
+        );
+        assert!(!writer.backup_path(3).exists());
+        assert_eq!(fs::read_dir(test_dir.path()).unwrap().count(), 4);
+
+        // Without a commit, the temporary file is deleted
+        let mut writer = writer;
+        writer.write_all(b"version = 5").unwrap();
+        drop(writer);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "version = 4");
+        assert_eq!(fs::read_dir(test_dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn dangling_symlink() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let link = test_dir.path().join("state.toml");
+        fs::create_dir(test_dir.path().join("state")).unwrap();
+        symlink("state/target.toml", &link).unwrap();
+
+        let mut writer = AtomicWriter::new(&link).unwrap();
+        assert_eq!(writer.path(), test_dir.path().join("state/target.toml"));
+        writer.write_all(b"version = 1").unwrap();
+        writer.commit().unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&link).unwrap(), "version = 1");
+
+        let looping = test_dir.path().join("loop");
+        symlink("loop", &looping).unwrap();
+        assert!(AtomicWriter::new(&looping).is_err());
+    }
+
+    // region: IMPORTS
+
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
@@ -0,0 +1,338 @@
+/// What a path is required to be, when checked with
+/// [`ValidPath::check()`](super::ValidPath::check) or
+/// [`ValidPaths::check_paths()`](super::ValidPaths::check_paths).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PathRequirement {
+    Exists,
+    Readable,
+    Writable,
+    Executable,
+    /// The path exists and is writable, or it can be created, since the
+    /// innermost existing directory containing it is writable.
+    Creatable,
+    File,
+    Dir,
+}
+
+/// Why a path does not meet a [`PathRequirement`].
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum PathIssue {
+    #[non_exhaustive]
+    #[snafu(display("no path was given"), visibility(pub(crate)))]
+    NoPath,
+
+    #[non_exhaustive]
+    #[snafu(display("{:?} does not exist", path), visibility(pub(crate)))]
+    NotFound { path: PathBuf },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("{:?} is a symbolic link to {:?}, which does not exist", path, target),
+        visibility(pub(crate))
+    )]
+    BrokenSymlink { path: PathBuf, target: PathBuf },
+
+    /// The path, or a directory containing it (the `component`), cannot be
+    /// accessed by the current user.
+    #[non_exhaustive]
+    #[snafu(
+        display(
+            "{:?} is not accessible, since permission is denied on {:?}",
+            path,
+            component
+        ),
+        visibility(pub(crate))
+    )]
+    PermissionDenied { path: PathBuf, component: PathBuf },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("{:?} is on a read-only filesystem", path),
+        visibility(pub(crate))
+    )]
+    ReadOnlyFilesystem { path: PathBuf },
+
+    /// The path, or what should be a directory containing it (the
+    /// `component`), is not a directory.
+    #[non_exhaustive]
+    #[snafu(
+        display(
+            "{:?} is not accessible, since {:?} is not a directory",
+            path,
+            component
+        ),
+        visibility(pub(crate))
+    )]
+    NotADirectory { path: PathBuf, component: PathBuf },
+
+    #[non_exhaustive]
+    #[snafu(display("{:?} is not a regular file", path), visibility(pub(crate)))]
+    NotAFile { path: PathBuf },
+
+    #[non_exhaustive]
+    #[snafu(
+        display("could not inspect {:?}: {source}", path),
+        visibility(pub(crate))
+    )]
+    Inspect { path: PathBuf, source: io::Error },
+}
+
+/// The result of checking every candidate path for a [`PathRequirement`], in
+/// order: either the path, or why it was skipped. Its `Display` lists every
+/// candidate, to explain to users why no path could be used.
+#[derive(Debug)]
+pub struct PathReport<P> {
+    candidates: Vec<Result<P, PathIssue>>,
+}
+
+impl<P> PathReport<P> {
+    pub(crate) fn new(candidates: Vec<Result<P, PathIssue>>) -> Self {
+        Self { candidates }
+    }
+
+    /// Every candidate, in order, with the reason why it was skipped.
+    pub fn candidates(&self) -> &[Result<P, PathIssue>] {
+        &self.candidates
+    }
+
+    /// The first candidate meeting the requirement.
+    pub fn first_valid(&self) -> Option<&P> {
+        self.valid().next()
+    }
+
+    /// Every candidate meeting the requirement.
+    pub fn valid(&self) -> impl Iterator<Item = &P> {
+        self.candidates
+            .iter()
+            .filter_map(|candidate| candidate.as_ref().ok())
+    }
+
+    /// Why the skipped candidates do not meet the requirement.
+    pub fn issues(&self) -> impl Iterator<Item = &PathIssue> {
+        self.candidates
+            .iter()
+            .filter_map(|candidate| candidate.as_ref().err())
+    }
+
+    /// The first candidate meeting the requirement, or the report if there
+    /// is none.
+    pub fn into_first_valid(mut self) -> Result<P, Self> {
+        match self.candidates.iter().position(Result::is_ok) {
+            Some(index) => Ok(self.candidates.swap_remove(index).ok().unwrap()),
+            None => Err(self),
+        }
+    }
+}
+
+impl<P> fmt::Display for PathReport<P>
+where
+    P: AsRef<Path>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.candidates.is_empty() {
+            return f.write_str("there are no candidate paths");
+        }
+        for (index, candidate) in self.candidates.iter().enumerate() {
+            if index > 0 {
+                f.write_str("\n")?;
+            }
+            match candidate {
+                Ok(path) => write!(f, "{:?}: valid", path.as_ref())?,
+                Err(issue) => write!(f, "skipped: {}", issue)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check that the path meets the requirement, or find out why not.
+pub(crate) fn check_path(path: &Path, requirement: PathRequirement) -> Result<(), PathIssue> {
+    if requirement == PathRequirement::Creatable {
+        let existing = path
+            .largest_valid_subset()
+            .context(NotFoundSnafu { path })?;
+        if existing != path && !existing.is_dir() {
+            return NotADirectorySnafu {
+                path,
+                component: existing,
+            }
+            .fail();
+        }
+        return check_access(existing, |path| permissions::is_writable(path));
+    }
+
+    let metadata = fs::metadata(path).map_err(|error| diagnose(path, error))?;
+    match requirement {
+        PathRequirement::Readable => check_access(path, |path| permissions::is_readable(path)),
+        PathRequirement::Writable => check_access(path, |path| permissions::is_writable(path)),
+        PathRequirement::Executable => check_access(path, |path| permissions::is_executable(path)),
+        PathRequirement::File if !metadata.is_file() => NotAFileSnafu { path }.fail(),
+        PathRequirement::Dir if !metadata.is_dir() => NotADirectorySnafu {
+            path,
+            component: path,
+        }
+        .fail(),
+        _ => Ok(()),
+    }
+}
+
+/// Check access to an existing path with a function like
+/// `permissions::is_writable`, which only fails for unexpected errors.
+fn check_access(
+    path: &Path,
+    is_accessible: fn(&Path) -> io::Result<bool>,
+) -> Result<(), PathIssue> {
+    match is_accessible(path) {
+        Ok(true) => Ok(()),
+        Ok(false) => PermissionDeniedSnafu {
+            path,
+            component: path,
+        }
+        .fail(),
+        Err(error) if error.kind() == io::ErrorKind::ReadOnlyFilesystem => {
+            ReadOnlyFilesystemSnafu { path }.fail()
+        }
+        Err(error) => Err(error).context(InspectSnafu { path }),
+    }
+}
+
+/// Turn the error from reading the metadata of a path into the reason why
+/// the path cannot be used, finding the component of the path at fault.
+fn diagnose(path: &Path, error: io::Error) -> PathIssue {
+    // The directories containing the path, from the outermost one
+    let mut directories = path
+        .ancestors()
+        .skip(1)
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .collect::<Vec<_>>();
+    directories.reverse();
+    let component = |is_at_fault: fn(&Path) -> bool| {
+        directories
+            .iter()
+            .find(|directory| is_at_fault(directory))
+            .map_or_else(|| path.to_owned(), |directory| directory.to_path_buf())
+    };
+
+    match error.kind() {
+        io::ErrorKind::NotFound => match fs::read_link(path) {
+            Ok(target) => PathIssue::BrokenSymlink {
+                path: path.to_owned(),
+                target,
+            },
+            Err(_) => PathIssue::NotFound {
+                path: path.to_owned(),
+            },
+        },
+        io::ErrorKind::PermissionDenied => PathIssue::PermissionDenied {
+            path: path.to_owned(),
+            component: component(|directory| {
+                directory.is_dir() && !permissions::is_executable(directory).unwrap_or(false)
+            }),
+        },
+        io::ErrorKind::NotADirectory => PathIssue::NotADirectory {
+            path: path.to_owned(),
+            component: component(|directory| directory.exists() && !directory.is_dir()),
+        },
+        _ => PathIssue::Inspect {
+            path: path.to_owned(),
+            source: error,
+        },
+    }
+}
+
+// region: IMPORTS
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use snafu::{OptionExt, ResultExt, Snafu};
+
+use super::ValidPath;
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(all(test, unix))]
+mod tests {
+    #[test]
+    fn check() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let file = test_dir.path().join("file");
+        let link = test_dir.path().join("link");
+        fs::write(&file, "").unwrap();
+        symlink(test_dir.path().join("missing"), &link).unwrap();
+
+        assert!(file.check(PathRequirement::File).is_ok());
+        assert!(test_dir
+            .path()
+            .join("a/b")
+            .check(PathRequirement::Creatable)
+            .is_ok());
+        assert!(matches!(
+            file.check(PathRequirement::Dir),
+            Err(PathIssue::NotADirectory { component, .. }) if component == file
+        ));
+        assert!(matches!(
+            test_dir.path().check(PathRequirement::File),
+            Err(PathIssue::NotAFile { .. })
+        ));
+        assert!(matches!(
+            link.check(PathRequirement::Readable),
+            Err(PathIssue::BrokenSymlink { target, .. }) if target == test_dir.path().join("missing")
+        ));
+        assert!(matches!(
+            file.join("config.toml").check(PathRequirement::Exists),
+            Err(PathIssue::NotADirectory { component, .. }) if component == file
+        ));
+        assert!(matches!(
+            file.join("config.toml").check(PathRequirement::Creatable),
+            Err(PathIssue::NotADirectory { component, .. }) if component == file
+        ));
+        assert!(matches!(
+            Option::<PathBuf>::None.check(PathRequirement::Exists),
+            Err(PathIssue::NoPath)
+        ));
+    }
+
+    #[test]
+    fn check_paths() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let file = test_dir.path().join("file");
+        fs::write(&file, "").unwrap();
+
+        let missing = test_dir.path().join("missing");
+        let report = [missing.clone(), test_dir.path().to_owned(), file.clone()]
+            .into_iter()
+            .check_paths(PathRequirement::File);
+        assert_eq!(report.first_valid(), Some(&file));
+        assert_eq!(report.issues().count(), 2);
+        assert_eq!(
+            report.to_string().lines().next(),
+            Some(format!("skipped: {:?} does not exist", missing).as_str())
+        );
+
+        let report = [None, Some(missing)]
+            .into_iter()
+            .check_paths(PathRequirement::Exists);
+        assert_eq!(report.candidates().len(), 2);
+        assert!(report.into_first_valid().is_err());
+    }
+
+    // region: IMPORTS
+
+    use std::os::unix::fs::symlink;
+
+    use super::{
+        super::{ValidPath, ValidPaths},
+        *,
+    };
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
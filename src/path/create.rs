@@ -0,0 +1,157 @@
+/// Create the directory at `path` and every missing directory containing
+/// it, with the given permissions (like 0700 for private directories),
+/// regardless of the umask. Only the directories which are created, after
+/// the [`largest_valid_subset()`](ValidPath::largest_valid_subset) of the
+/// path, are given the permissions: existing directories are left as they
+/// are. If a directory cannot be created, the error names it.
+pub fn ensure_dir(path: impl AsRef<Path>, mode: u32) -> Result<(), Error> {
+    let path = path.as_ref();
+    let existing = path.largest_valid_subset().unwrap_or(Path::new(""));
+    if !existing.as_os_str().is_empty() && !existing.is_dir() {
+        return Err(not_a_directory(existing)).context(CreateDirectorySnafu { path: existing });
+    }
+
+    let mut directory = existing.to_owned();
+    for component in path.strip_prefix(existing).unwrap_or(path).components() {
+        directory.push(component);
+        match DirBuilder::new().mode(mode).create(&directory) {
+            // The umask may have removed permissions from the mode
+            Ok(()) => fs::set_permissions(&directory, Permissions::from_mode(mode))
+                .context(CreateDirectorySnafu { path: &directory })?,
+            // Another process may have created the directory since
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists && directory.is_dir() => {}
+            Err(error) => return Err(error).context(CreateDirectorySnafu { path: &directory }),
+        }
+    }
+    Ok(())
+}
+
+/// Create a new file at `path` which only the current user can read and
+/// write (mode 0600). This fails if anything already exists at `path`, even
+/// a symbolic link, so that a file prepared by another user is never
+/// written to. The directory containing the file must exist (see
+/// [`ensure_dir()`]). If the file cannot be created, the error names the
+/// component of the path at fault, like a missing directory.
+pub fn create_file_private(path: impl AsRef<Path>) -> Result<File, Error> {
+    let path = path.as_ref();
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC)
+        .open(path)
+        .map_err(|error| {
+            let component = failing_component(path, &error);
+            Error::CreateFile {
+                path: path.to_owned(),
+                component,
+                source: error,
+            }
+        })
+}
+
+/// The component of `path` which made creating it fail with `error`.
+fn failing_component(path: &Path, error: &io::Error) -> PathBuf {
+    // The directories containing the path, from the outermost one
+    let mut directories = path
+        .ancestors()
+        .skip(1)
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .collect::<Vec<_>>();
+    directories.reverse();
+    let component = match error.kind() {
+        io::ErrorKind::NotFound => directories.iter().find(|directory| !directory.exists()),
+        io::ErrorKind::NotADirectory => directories
+            .iter()
+            .find(|directory| directory.exists() && !directory.is_dir()),
+        io::ErrorKind::PermissionDenied => directories
+            .iter()
+            .find(|directory| !directory.is_executable())
+            .or(directories.last()),
+        _ => None,
+    };
+    component.map_or_else(|| path.to_owned(), |directory| directory.to_path_buf())
+}
+
+fn not_a_directory(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotADirectory,
+        format!("{:?} is not a directory", path),
+    )
+}
+
+// region: IMPORTS
+
+use std::{
+    fs::{self, DirBuilder, File, OpenOptions, Permissions},
+    io,
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use snafu::ResultExt;
+
+use super::{CreateDirectorySnafu, Error, ValidPath};
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn ensure_dir() {
+        let test_dir = tempfile::tempdir().unwrap();
+        fs::set_permissions(test_dir.path(), Permissions::from_mode(0o755)).unwrap();
+        let state_dir = test_dir.path().join("state/myapp/logs");
+
+        super::ensure_dir(&state_dir, 0o700).unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().mode() & 0o777;
+        assert_eq!(mode(&state_dir), 0o700);
+        assert_eq!(mode(&test_dir.path().join("state")), 0o700);
+        assert_eq!(mode(test_dir.path()), 0o755);
+        super::ensure_dir(&state_dir, 0o700).unwrap();
+
+        let file = test_dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert!(matches!(
+            super::ensure_dir(file.join("directory"), 0o700),
+            Err(Error::CreateDirectory { path, .. }) if path == file
+        ));
+    }
+
+    #[test]
+    fn create_file_private() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let path = test_dir.path().join("secret");
+
+        super::create_file_private(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().mode() & 0o777, 0o600);
+        assert!(matches!(
+            super::create_file_private(&path),
+            Err(Error::CreateFile { component, .. }) if component == path
+        ));
+
+        let link = test_dir.path().join("link");
+        symlink(test_dir.path().join("target"), &link).unwrap();
+        assert!(super::create_file_private(&link).is_err());
+        assert!(!test_dir.path().join("target").exists());
+
+        let missing = test_dir.path().join("missing");
+        assert!(matches!(
+            super::create_file_private(missing.join("directory/secret")),
+            Err(Error::CreateFile { component, .. }) if component == missing
+        ));
+    }
+
+    // region: IMPORTS
+
+    use std::os::unix::fs::{symlink, MetadataExt};
+
+    use super::*;
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS
@@ -0,0 +1,224 @@
+/// Whether a lock can be held by several processes at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// For readers: any number of processes can hold a shared lock, as long
+    /// as no process holds an exclusive lock.
+    Shared,
+    /// For writers: a single process can hold an exclusive lock, as long as
+    /// no process holds any lock.
+    Exclusive,
+}
+
+impl LockMode {
+    fn operation(self) -> libc::c_int {
+        match self {
+            LockMode::Shared => libc::LOCK_SH,
+            LockMode::Exclusive => libc::LOCK_EX,
+        }
+    }
+}
+
+/// An advisory lock on a file (`flock`), held until it is dropped. The lock
+/// only coordinates processes which lock the same file, it does not prevent
+/// other processes from accessing any file.
+///
+/// The lock file is created if it is missing. By convention, processes
+/// sharing the state or config files of an application lock files in its
+/// runtime directory, named after the files they access, see
+/// [`AppDirs::lock_path()`](super::AppDirs::lock_path). Readers hold a
+/// [`Shared`](LockMode::Shared) lock while reading, and writers an
+/// [`Exclusive`](LockMode::Exclusive) lock while writing:
+///
+/// ```no_run
+/// # use dapp::path::{AppDirs, AtomicWriter, FileLock, LockMode};
+/// # fn main() -> Result<(), dapp::path::Error> {
+/// # let app_dirs = AppDirs::new("myapp")?;
+/// let _lock = FileLock::lock(app_dirs.lock_path("history")?, LockMode::Exclusive)?;
+/// let mut writer = AtomicWriter::new(app_dirs.place_state_file("history")?)?;
+/// // ...
+/// # writer.commit()
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+    mode: LockMode,
+    file: File,
+}
+
+impl FileLock {
+    /// Lock the file at `path`, waiting as long as another process holds a
+    /// conflicting lock.
+    pub fn lock(path: impl AsRef<Path>, mode: LockMode) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = open(path)?;
+        // Waiting is interrupted by signals
+        while let Err(error) = flock(&file, mode.operation()) {
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error).context(LockSnafu { path });
+            }
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            mode,
+            file,
+        })
+    }
+
+    /// Lock the file at `path`, or return `None` at once if another process
+    /// holds a conflicting lock.
+    pub fn try_lock(path: impl AsRef<Path>, mode: LockMode) -> Result<Option<Self>, Error> {
+        let path = path.as_ref();
+        let file = open(path)?;
+        Self::try_lock_file(path, mode, file).map(|lock| lock.ok())
+    }
+
+    /// Lock the file at `path`, or return `None` if another process still
+    /// holds a conflicting lock after the timeout.
+    pub fn lock_timeout(
+        path: impl AsRef<Path>,
+        mode: LockMode,
+        timeout: Duration,
+    ) -> Result<Option<Self>, Error> {
+        let path = path.as_ref();
+        let deadline = Instant::now() + timeout;
+        let mut file = open(path)?;
+        // `flock()` cannot time out, so poll, less often as time passes
+        let mut interval = Duration::from_millis(1);
+        loop {
+            file = match Self::try_lock_file(path, mode, file)? {
+                Ok(lock) => return Ok(Some(lock)),
+                Err(file) => file,
+            };
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            thread::sleep(interval.min(deadline - now));
+            interval = (interval * 2).min(Duration::from_millis(100));
+        }
+    }
+
+    /// The lock, or the file back if another process holds a conflicting
+    /// lock.
+    fn try_lock_file(path: &Path, mode: LockMode, file: File) -> Result<Result<Self, File>, Error> {
+        match flock(&file, mode.operation() | libc::LOCK_NB) {
+            Ok(()) => Ok(Ok(Self {
+                path: path.to_owned(),
+                mode,
+                file,
+            })),
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => Ok(Err(file)),
+            Err(error) => Err(error).context(LockSnafu { path }),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn mode(&self) -> LockMode {
+        self.mode
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Closing the file would release the lock too, but not if the file
+        // descriptor was inherited by a child process
+        let _ = flock(&self.file, libc::LOCK_UN);
+    }
+}
+
+/// Open (or create) a lock file, private to the current user.
+fn open(path: &Path) -> Result<File, Error> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC)
+        .open(path)
+        .context(LockSnafu { path })
+}
+
+fn flock(file: &File, operation: libc::c_int) -> io::Result<()> {
+    // SAFETY: the file descriptor is valid as long as the file is open
+    match unsafe { libc::flock(file.as_raw_fd(), operation) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+// region: IMPORTS
+
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    os::unix::{fs::OpenOptionsExt, io::AsRawFd},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use snafu::ResultExt;
+
+use super::{Error, LockSnafu};
+
+// endregion: IMPORTS
+
+// region: TESTS
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn file_lock() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let path = test_dir.path().join("state.lock");
+
+        let shared = FileLock::lock(&path, LockMode::Shared).unwrap();
+        assert!(FileLock::try_lock(&path, LockMode::Shared)
+            .unwrap()
+            .is_some());
+        assert!(FileLock::try_lock(&path, LockMode::Exclusive)
+            .unwrap()
+            .is_none());
+        let start = Instant::now();
+        assert!(
+            FileLock::lock_timeout(&path, LockMode::Exclusive, Duration::from_millis(50))
+                .unwrap()
+                .is_none()
+        );
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        let waiting = thread::spawn({
+            let path = path.clone();
+            move || {
+                FileLock::lock_timeout(&path, LockMode::Exclusive, Duration::from_secs(10))
+                    .unwrap()
+                    .map(|lock| lock.mode())
+            }
+        });
+        thread::sleep(Duration::from_millis(20));
+        drop(shared);
+        assert_eq!(waiting.join().unwrap(), Some(LockMode::Exclusive));
+
+        let exclusive = FileLock::lock(&path, LockMode::Exclusive).unwrap();
+        assert!(FileLock::try_lock(&path, LockMode::Shared)
+            .unwrap()
+            .is_none());
+        drop(exclusive);
+        assert!(FileLock::try_lock(&path, LockMode::Shared)
+            .unwrap()
+            .is_some());
+    }
+
+    // region: IMPORTS
+
+    use super::*;
+
+    // endregion: IMPORTS
+}
+
+// endregion: TESTS